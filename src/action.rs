@@ -1,16 +1,25 @@
 use std::{fmt::Display, process::ExitCode};
 
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
 use crate::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub enum Action {
     Pass,
     Act(CardInstance, CharacterId),
     Move(CharacterId, GridLocation),
     Take(CharacterId, GridLocation, TakeActionItem),
+    /// Takes back the character's last action this turn
+    Undo,
+    /// Picks the cards for the character's pending card choice
+    Choose(Vec<CardInstance>),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub enum TakeActionItem {
     Card(usize, usize),
     Object(usize, usize),
@@ -25,6 +34,117 @@ impl TakeActionItem {
     }
 }
 
+/// Why the battle refused to carry out an action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum ActionRejection {
+    /// Characters may only move or take items for themselves
+    NotSelf {
+        character_id: CharacterId,
+    },
+    UnknownCharacter {
+        character_id: CharacterId,
+    },
+    UnknownCard {
+        card_id: CardId,
+    },
+    CardNotInHand {
+        card: CardInstance,
+    },
+    NoActionsRemaining,
+    NotEnoughEnergy {
+        energy: u64,
+        cost: u64,
+    },
+    NoMovementRemaining,
+    WrongTarget {
+        expected: Target,
+    },
+    TargetDead {
+        target_id: CharacterId,
+    },
+    OutOfRange {
+        distance: u64,
+        range: u64,
+    },
+    NotOnBoard,
+    OutOfBounds {
+        location: GridLocation,
+    },
+    NotAdjacent {
+        location: GridLocation,
+    },
+    Blocked {
+        location: GridLocation,
+    },
+    OutOfReach {
+        distance: GridDimension,
+        reach: GridDimension,
+    },
+    ItemNotFound {
+        location: GridLocation,
+    },
+    NothingToUndo,
+    /// Actions cannot be undone once one of them revealed a random value
+    RandomRevealed,
+    /// A card choice has to be made before anything else happens
+    ChoicePending,
+    NothingToChoose,
+    InvalidChoice {
+        count: usize,
+    },
+}
+
+impl Display for ActionRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotSelf { character_id } => {
+                write!(f, "Cannot act on behalf of character {character_id}")
+            }
+            Self::UnknownCharacter { character_id } => {
+                write!(f, "Unknown character {character_id}")
+            }
+            Self::UnknownCard { card_id } => write!(f, "Unknown card {card_id}"),
+            Self::CardNotInHand { .. } => f.write_str("Card is not in hand"),
+            Self::NoActionsRemaining => f.write_str("No actions remaining"),
+            Self::NotEnoughEnergy { energy, cost } => {
+                write!(f, "Not enough energy ({energy} < {cost})")
+            }
+            Self::NoMovementRemaining => f.write_str("No movement remaining"),
+            Self::WrongTarget { expected } => f.write_str(match expected {
+                Target::Me => "Card can only target yourself",
+                Target::Others => "Card cannot target yourself",
+                Target::Any => "Card cannot target that character",
+            }),
+            Self::TargetDead { .. } => f.write_str("Target is dead"),
+            Self::OutOfRange { distance, range } => {
+                write!(f, "Target out of range ({distance} > {range})")
+            }
+            Self::NotOnBoard => f.write_str("Character is not on the board"),
+            Self::OutOfBounds { location } => {
+                write!(f, "({}, {}) is off the board", location.x, location.y)
+            }
+            Self::NotAdjacent { location } => {
+                write!(f, "({}, {}) is not adjacent", location.x, location.y)
+            }
+            Self::Blocked { location } => {
+                write!(f, "({}, {}) is blocked", location.x, location.y)
+            }
+            Self::OutOfReach { distance, reach } => {
+                write!(f, "Item out of reach ({distance} > {reach})")
+            }
+            Self::ItemNotFound { location } => {
+                write!(f, "Item not found at ({}, {})", location.x, location.y)
+            }
+            Self::NothingToUndo => f.write_str("Nothing to undo this turn"),
+            Self::RandomRevealed => f.write_str("Cannot undo after a random roll was revealed"),
+            Self::ChoicePending => f.write_str("Cards have to be chosen first"),
+            Self::NothingToChoose => f.write_str("Nothing to choose"),
+            Self::InvalidChoice { count } => write!(f, "Choose {count} of the offered cards"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ActionFailure {
     pub message: String,
@@ -10,6 +10,21 @@ pub trait Actor: Sync {
     /// Perform action on turn
     async fn act(&self, battle: &Battle) -> ActionResult;
 
+    /// Called when the battle refuses an action returned from `act`. `act` is called again after.
+    async fn on_action_rejected(
+        &self,
+        _battle: &Battle,
+        _action: &Action,
+        _rejection: &ActionRejection,
+    ) {
+    }
+
+    /// Picks cards for a choice a card left the character with, e.g. which cards to discard.
+    /// Invalid picks are replaced with the default pick.
+    async fn choose_cards(&self, _battle: &Battle, choice: &CardChoice) -> Vec<CardInstance> {
+        choice.default_pick()
+    }
+
     /// Called when the game is over
     async fn on_game_over(&self, battle: &Battle);
 }
@@ -1,14 +1,21 @@
 use crate::{
-    Action, ActionError, Actor, Attack, BattleText, Board, BoardItem, Card, CardAction, CardId,
-    CardInstance, CardInstanceId, Character, CharacterId, Content, DeclareWrappedType, Effect,
-    EffectId, GridLocation, HashMapExt, Health, Object, ObjectId, ObjectInstance, RandomProvider,
-    TakeActionItem, Target, Trigger, U64Range, VecExt, battle_file, battle_markup,
+    Action, ActionError, ActionLogEntry, ActionRejection, Actor, AiPersonality, Attack,
+    BattleEvent, BattleObserver, BattleText, Board, BoardItem, Card, CardAction, CardChoice,
+    CardChoiceKind, CardId, CardInstance, CardInstanceId, Character, CharacterId, Content,
+    CountingRandomProvider, DeclareWrappedType, Defense, Effect, EffectId, EffectInstance,
+    GridLocation, HashMapExt, Health, Object, ObjectId, ObjectInstance, Pile, RandomPickCount,
+    RandomPicker, RandomProvider, TakeActionItem, Target, Trigger, U64Range, UndoHistory,
+    UndoSnapshot, VecExt, battle_file, create_ai_actor,
 };
 use schemars::JsonSchema;
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::PathBuf;
 use std::process::ExitCode;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tokio::time::timeout_at;
 
 DeclareWrappedType!(TeamId, id, u64);
 
@@ -19,11 +26,51 @@ pub struct Team {
     pub name: String,
 }
 
+/// How long a character may take for their turn and what happens once the time is up
+#[derive(Debug, Clone)]
+pub struct TurnTimer {
+    pub limit: Duration,
+    pub on_timeout: TurnTimeout,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TurnTimeout {
+    Pass,
+    /// An AI with the personality plays the rest of the turn
+    Ai(Option<AiPersonality>),
+}
+
+/// The order characters take their turns in each round
+#[derive(Debug, Clone)]
+pub enum TurnOrder {
+    /// The order of the actors, which is team by team
+    TeamByTeam,
+    /// One member of each team at a time
+    Interleaved,
+    /// Highest initiative first, with `roll` added to every character's initiative each round
+    Initiative { roll: Option<U64Range> },
+    /// Characters gain their speed in readiness every round and take a turn for every
+    /// `READINESS_PER_TURN` of it, spread over the round by how soon they reach it
+    Speed,
+}
+
+/// Readiness spent on a turn with the speed turn order
+pub const READINESS_PER_TURN: u64 = 10;
+
 #[derive(Debug)]
 struct Turn {
     character: CharacterId,
 }
 
+/// A card whose actions are partway through running, waiting on a card choice
+#[derive(Debug, Clone)]
+pub(crate) struct CardInPlay {
+    actor: CharacterId,
+    target_id: CharacterId,
+    card_instance: CardInstance,
+    actions: VecDeque<CardAction>,
+}
+
 type StoryCard = battle_file::StoryCard;
 pub type EndConditionType = battle_file::EndConditionType;
 
@@ -53,27 +100,68 @@ pub struct EndCondition {
 pub struct Battle {
     #[serde(skip)]
     pub actors: Vec<(TeamId, Box<dyn Actor>)>,
+    #[serde(skip)]
+    pub observers: Vec<Box<dyn BattleObserver>>,
     pub characters: HashMap<CharacterId, Character>,
     pub introduction: Option<StoryCard>,
-    pub teams: Vec<Team>,
+    pub teams: Arc<Vec<Team>>,
     pub history: Vec<BattleText>,
+    /// Everything that has happened in the battle, in order. `history` is rendered from these.
+    pub events: Vec<BattleEvent>,
+    /// Every action submitted by an actor, in the order it was received
+    #[serde(skip)]
+    pub action_log: Vec<ActionLogEntry>,
     #[serde(skip)]
     pub random_provider: Box<dyn RandomProvider>,
+    /// How many values `random_provider` has produced
+    #[serde(skip)]
+    pub random_picks: RandomPickCount,
     pub round: u16,
-    pub cards: HashMap<CardId, Card>,
-    pub effects: HashMap<EffectId, Effect>,
-    pub objects: HashMap<ObjectId, Object>,
+    pub cards: Arc<HashMap<CardId, Card>>,
+    pub effects: Arc<HashMap<EffectId, Effect>>,
+    pub objects: Arc<HashMap<ObjectId, Object>>,
     pub default_turn_actions: u64,
     #[serde(skip)]
     pub asset_directory: Option<PathBuf>,
     pub board: Board,
     pub background_image: Option<String>,
-    pub end_conditions: Vec<EndCondition>,
+    pub end_conditions: Arc<Vec<EndCondition>>,
     pub end_state: Option<EndConditionType>,
+    /// Time limits for the turns of characters. Characters without one may take as long as they
+    /// like.
+    #[serde(skip)]
+    pub turn_timers: HashMap<CharacterId, TurnTimer>,
+    /// When the current turn runs out of time, if it is limited
+    #[serde(skip)]
+    pub turn_deadline: Option<Instant>,
+    /// Characters controlled by players. Only their actions are recorded to be undone.
+    #[serde(skip)]
+    pub players: HashSet<CharacterId>,
+    /// Actions the acting character may take back
+    #[serde(skip)]
+    pub undo_history: UndoHistory,
+    #[serde(skip)]
+    pub turn_order: TurnOrder,
+    /// Set while effects triggered by a reaction run, so they cannot set off more reactions
+    #[serde(skip)]
+    pub(crate) reacting: bool,
+    /// Cards a character has to pick before the battle goes on
+    #[serde(skip)]
+    pub pending_choice: Option<CardChoice>,
+    #[serde(skip)]
+    pub(crate) card_in_play: Option<CardInPlay>,
 }
 
 unsafe impl Sync for Battle {}
 
+/// Whether `content` is the item a take action with `item_id` refers to
+fn content_matches(content: &Content, item_id: usize) -> bool {
+    match content {
+        Content::Object(instance) => instance.object_id.id == item_id,
+        Content::Card(instance) => instance.card_id.id == item_id,
+    }
+}
+
 impl Battle {
     pub fn get_character(&self, actor: &dyn Actor) -> &Character {
         &self.characters[actor.get_character_id()]
@@ -88,22 +176,132 @@ impl Battle {
         None
     }
 
+    /// Records an event and adds its rendered text to the history
+    fn emit(&mut self, event: BattleEvent) {
+        if let Some(text) = event.to_battle_text(self) {
+            self.history.push(text);
+        }
+        self.notify_observers(|observer, battle| observer.on_event(battle, &event));
+        self.events.push(event);
+    }
+
+    pub fn add_observer(&mut self, observer: Box<dyn BattleObserver>) {
+        self.observers.push(observer);
+    }
+
+    fn notify_observers(&self, notify: impl Fn(&dyn BattleObserver, &Battle)) {
+        for observer in &self.observers {
+            notify(observer.as_ref(), self);
+        }
+    }
+
+    /// Copies the battle without its actors, observers, history or action log so it can be
+    /// played forward without affecting this battle. Static definitions such as cards are shared.
+    pub fn clone_state(&self, random_provider: Box<dyn RandomProvider>) -> Battle {
+        let random_provider = CountingRandomProvider::new(random_provider);
+        Battle {
+            actors: vec![],
+            observers: vec![],
+            characters: self.characters.clone(),
+            introduction: self.introduction.clone(),
+            teams: self.teams.clone(),
+            history: vec![],
+            events: vec![],
+            action_log: vec![],
+            random_picks: random_provider.count(),
+            random_provider: Box::new(random_provider),
+            round: self.round,
+            cards: self.cards.clone(),
+            effects: self.effects.clone(),
+            objects: self.objects.clone(),
+            default_turn_actions: self.default_turn_actions,
+            asset_directory: self.asset_directory.clone(),
+            board: self.board.clone(),
+            background_image: self.background_image.clone(),
+            end_conditions: self.end_conditions.clone(),
+            end_state: self.end_state,
+            turn_timers: HashMap::new(),
+            turn_deadline: None,
+            players: self.players.clone(),
+            undo_history: UndoHistory::default(),
+            turn_order: self.turn_order.clone(),
+            reacting: false,
+            pending_choice: self.pending_choice.clone(),
+            card_in_play: self.card_in_play.clone(),
+        }
+    }
+
     pub fn get_team_from_id(&self, id: TeamId) -> Option<&Team> {
         self.teams.iter().find(|&team| team.id == id)
     }
 
-    fn build_turns(&self) -> Vec<Turn> {
-        let mut ret = vec![];
-        for (_team_id, actor) in &self.actors {
-            if self.get_character(actor.as_ref()).is_dead() {
-                continue;
+    fn build_turns(&mut self) -> Vec<Turn> {
+        // Living characters in the order of their actors
+        let characters: Vec<(TeamId, CharacterId)> = self
+            .actors
+            .iter()
+            .filter(|(_, actor)| !self.get_character(actor.as_ref()).is_dead())
+            .map(|(team_id, actor)| (*team_id, *actor.get_character_id()))
+            .collect();
+
+        let order: Vec<CharacterId> = match &self.turn_order {
+            TurnOrder::TeamByTeam => characters.iter().map(|(_, id)| *id).collect(),
+            TurnOrder::Interleaved => {
+                let mut teams: Vec<Vec<CharacterId>> = vec![];
+                for team in self.teams.iter() {
+                    teams.push(
+                        characters
+                            .iter()
+                            .filter(|(team_id, _)| *team_id == team.id)
+                            .map(|(_, id)| *id)
+                            .collect(),
+                    );
+                }
+                let longest = teams.iter().map(Vec::len).max().unwrap_or(0);
+                (0..longest)
+                    .flat_map(|index| teams.iter().filter_map(move |team| team.get(index)))
+                    .copied()
+                    .collect()
+            }
+            TurnOrder::Initiative { roll } => {
+                let mut rolled: Vec<(u64, CharacterId)> = characters
+                    .iter()
+                    .map(|(_, id)| {
+                        let bonus = roll
+                            .as_ref()
+                            .map_or(0, |roll| roll.resolve(self.random_provider.as_ref()));
+                        (self.characters[id].initiative + bonus, *id)
+                    })
+                    .collect();
+                // Stable, so ties keep the order of the actors
+                rolled.sort_by(|(a, _), (b, _)| b.cmp(a));
+                rolled.into_iter().map(|(_, id)| id).collect()
+            }
+            TurnOrder::Speed => {
+                // When in the round each turn happens, as the fraction of the round it takes to
+                // reach the readiness for it
+                let mut timeline: Vec<(u64, u64, CharacterId)> = vec![];
+                for (_, id) in &characters {
+                    let character = self.characters.require_mut(id);
+                    let readiness = character.readiness + character.speed;
+                    let mut needed = READINESS_PER_TURN;
+                    while needed <= readiness {
+                        timeline.push((needed - character.readiness, character.speed, *id));
+                        needed += READINESS_PER_TURN;
+                    }
+                    character.readiness = readiness % READINESS_PER_TURN;
+                }
+                timeline.sort_by(|(a_gain, a_speed, _), (b_gain, b_speed, _)| {
+                    (a_gain * b_speed).cmp(&(b_gain * a_speed))
+                });
+                timeline.into_iter().map(|(_, _, id)| id).collect()
             }
+        };
 
-            ret.push(Turn {
-                character: *actor.get_character_id(),
-            });
-        }
-        ret
+        order
+            .into_iter()
+            .map(|character| Turn { character })
+            .collect()
     }
 
     pub fn get_actor(&self, character_id: &CharacterId) -> Option<&dyn Actor> {
@@ -134,8 +332,8 @@ impl Battle {
             .unwrap_or_else(|| panic!("Unable to find actor with character id: {character_id}"))
     }
 
-    fn did_battle_reach_end_condition(&self) -> Option<EndConditionType> {
-        for end_condition in &self.end_conditions {
+    pub fn did_battle_reach_end_condition(&self) -> Option<EndConditionType> {
+        for end_condition in self.end_conditions.iter() {
             if self.evaluate_condition(&end_condition.condition) {
                 return Some(end_condition.condition_type);
             }
@@ -144,7 +342,7 @@ impl Battle {
     }
 
     /// Returns true if the condition is met.
-    fn evaluate_condition(&self, condition: &EndConditionCriterion) -> bool {
+    pub fn evaluate_condition(&self, condition: &EndConditionCriterion) -> bool {
         match condition {
             EndConditionCriterion::TeamMemberDeath { ids } => {
                 ids.iter().all(|&id| self.characters[&id].is_dead())
@@ -179,218 +377,582 @@ impl Battle {
         }
     }
 
-    fn is_in_range(
-        &self,
-        range: u64,
-        character: CharacterId,
-        target_character: CharacterId,
-    ) -> bool {
+    fn get_characters_in_range(&self, location: GridLocation, range: u64) -> Vec<CharacterId> {
         self.board
-            .distance(
-                BoardItem::Character(character),
-                BoardItem::Character(target_character),
-            )
-            .unwrap_or(0)
-            <= range
+            .find_chars_in_range(location, range.try_into().unwrap())
     }
 
-    fn get_characters_in_range(&self, location: GridLocation, range: u64) -> Vec<CharacterId> {
+    fn find_character(&self, character_id: CharacterId) -> Result<GridLocation, ActionRejection> {
         self.board
-            .find_chars_in_range(location, range.try_into().unwrap())
+            .find(&BoardItem::Character(character_id))
+            .map(|(x, y)| GridLocation { x, y })
+            .ok_or(ActionRejection::NotOnBoard)
     }
 
-    /// Attempts to carry out the action. If the action (legal or no) consumes an action, returns true
-    fn handle_action(&mut self, actor: &CharacterId, action: Action) -> bool {
-        let character = &self.characters[actor];
+    /// Checks whether `actor` may currently perform the action without changing the battle
+    pub fn validate_action(
+        &self,
+        actor: &CharacterId,
+        action: &Action,
+    ) -> Result<(), ActionRejection> {
+        let character = self
+            .characters
+            .get(actor)
+            .ok_or(ActionRejection::UnknownCharacter {
+                character_id: *actor,
+            })?;
+        if let Some(choice) = &self.pending_choice {
+            return match action {
+                Action::Choose(picked) if choice.character_id == *actor => {
+                    if choice.is_valid(picked) {
+                        Ok(())
+                    } else {
+                        Err(ActionRejection::InvalidChoice {
+                            count: choice.count,
+                        })
+                    }
+                }
+                Action::Choose(_) => Err(ActionRejection::NothingToChoose),
+                _ => Err(ActionRejection::ChoicePending),
+            };
+        }
         match action {
-            Action::Pass => {
-                self.history.push(battle_markup![
-                    @id(&character.name),
-                    " took no action",
-                ]);
-                let character = self.characters.require_mut(actor);
-                character.remaining_actions = 0;
-                character.movement = 0;
-
-                true
-            }
+            Action::Pass => Ok(()),
+            Action::Choose(_) => Err(ActionRejection::NothingToChoose),
+            Action::Undo => self.undo_history.check(),
             Action::Move(target, location) => {
-                if actor != &target || character.movement == 0 {
-                    return false;
+                if actor != target {
+                    return Err(ActionRejection::NotSelf {
+                        character_id: *target,
+                    });
                 }
-
-                if let Some((x, y)) = self.board.find(&BoardItem::Character(target))
-                    && location.is_adjacent(&GridLocation { x, y })
-                    && !matches!(
-                        self.board.grid.get(location.x, location.y),
-                        Some(BoardItem::Character(_) | BoardItem::Inert)
-                    )
-                {
-                    self.characters.require_mut(&target).movement -= 1;
-
-                    self.board.grid.clear(x, y);
-
-                    let prev_contents =
-                        self.board
-                            .grid
-                            .set(location.x, location.y, BoardItem::Character(target));
-                    match prev_contents {
-                        None => {}
-                        Some(BoardItem::Inert) => {
-                            panic!("Inert should not be in the way of movement");
-                        }
-                        Some(BoardItem::Card(card_id)) => {
-                            self.characters.require_mut(&target).hand.push(card_id);
-                        }
-                        Some(BoardItem::Character(_)) => {
-                            panic!("Character should not be in the way of movement");
-                        }
-                    }
-
-                    return true;
+                if character.movement == 0 {
+                    return Err(ActionRejection::NoMovementRemaining);
                 }
-
-                false
+                let current_location = self.find_character(*target)?;
+                if !self.board.grid.is_valid(location.x, location.y) {
+                    return Err(ActionRejection::OutOfBounds {
+                        location: location.clone(),
+                    });
+                }
+                if !location.is_adjacent(&current_location) {
+                    return Err(ActionRejection::NotAdjacent {
+                        location: location.clone(),
+                    });
+                }
+                if matches!(
+                    self.board.grid.get(location.x, location.y),
+                    Some(BoardItem::Character(_) | BoardItem::Inert)
+                ) {
+                    return Err(ActionRejection::Blocked {
+                        location: location.clone(),
+                    });
+                }
+                Ok(())
             }
             Action::Act(card_instance, target_id) => {
-                let card = &self.cards[&card_instance.card_id];
-                let actual_target = if *actor == target_id {
+                let card =
+                    self.cards
+                        .get(&card_instance.card_id)
+                        .ok_or(ActionRejection::UnknownCard {
+                            card_id: card_instance.card_id,
+                        })?;
+                if !character.hand.contains(card_instance) {
+                    return Err(ActionRejection::CardNotInHand {
+                        card: *card_instance,
+                    });
+                }
+                if character.remaining_actions == 0 {
+                    return Err(ActionRejection::NoActionsRemaining);
+                }
+                if character.energy < card.cost {
+                    return Err(ActionRejection::NotEnoughEnergy {
+                        energy: character.energy,
+                        cost: card.cost,
+                    });
+                }
+
+                let target_character =
+                    self.characters
+                        .get(target_id)
+                        .ok_or(ActionRejection::UnknownCharacter {
+                            character_id: *target_id,
+                        })?;
+                let actual_target = if actor == target_id {
                     Target::Me
                 } else {
                     Target::Others
                 };
-
                 if !card.target().is_super_set(&actual_target) {
-                    return false;
+                    return Err(ActionRejection::WrongTarget {
+                        expected: card.target(),
+                    });
                 }
-
-                let target_character = &self.characters[&target_id];
                 if target_character.is_dead() {
-                    return false;
+                    return Err(ActionRejection::TargetDead {
+                        target_id: *target_id,
+                    });
+                }
+
+                let distance = self
+                    .board
+                    .distance(
+                        BoardItem::Character(*actor),
+                        BoardItem::Character(*target_id),
+                    )
+                    .unwrap_or(0);
+                if distance > card.range {
+                    return Err(ActionRejection::OutOfRange {
+                        distance,
+                        range: card.range,
+                    });
+                }
+                Ok(())
+            }
+            Action::Take(character_id, location, item) => {
+                if character_id != actor {
+                    // Currently only the actor can take for themself
+                    return Err(ActionRejection::NotSelf {
+                        character_id: *character_id,
+                    });
                 }
 
-                if !self.is_in_range(card.range, *actor, target_id) {
-                    return false;
+                let distance = location.distance(&self.find_character(*character_id)?);
+                if distance > character.reach_distance() {
+                    return Err(ActionRejection::OutOfReach {
+                        distance,
+                        reach: character.reach_distance(),
+                    });
                 }
 
-                if character.remaining_actions == 0 {
-                    return false;
-                }
-
-                let history_entry = battle_markup![
-                    @id(&character.name),
-                    " used ",
-                    @attack(&card.name),
-                    " on ",
-                    @id(&target_character.name),
-                    ". "
-                ];
-                self.history.push(history_entry);
-
-                self.characters.require_mut(actor).remaining_actions -= 1;
-
-                for action in card.actions.clone() {
-                    // If the action specifically targets me, then force it to target the actor
-                    // rather than the potentially other target.
-                    let target_id = if action.target() == &Target::Me {
-                        actor
-                    } else {
-                        &target_id
-                    };
+                let (item_id, item_instance_id) = item.id();
+                let found = match self.board.grid.get(location.x, location.y) {
+                    None | Some(BoardItem::Inert) => false,
+                    Some(BoardItem::Card(card_instance)) => {
+                        *card_instance
+                            == CardInstance::new(
+                                CardId::new(*item_id),
+                                CardInstanceId::new(*item_instance_id),
+                            )
+                    }
+                    Some(BoardItem::Character(loc_character_id)) => self.characters
+                        [loc_character_id]
+                        .contains
+                        .iter()
+                        .any(|content| content_matches(content, *item_id)),
+                };
+                if !found {
+                    return Err(ActionRejection::ItemNotFound {
+                        location: location.clone(),
+                    });
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Every action `character_id` may currently perform. Each returned action passes
+    /// `validate_action`.
+    pub fn legal_actions(&self, character_id: CharacterId) -> Vec<Action> {
+        let Some(character) = self.characters.get(&character_id) else {
+            return vec![];
+        };
+        if let Some(choice) = &self.pending_choice {
+            return if choice.character_id == character_id {
+                vec![Action::Choose(choice.default_pick())]
+            } else {
+                vec![]
+            };
+        }
+        let mut candidates = vec![];
+
+        // Sorted so the enumeration is deterministic for AIs picking from it
+        let mut target_ids: Vec<CharacterId> = self.characters.keys().copied().collect();
+        target_ids.sort();
+        for card_instance in &character.hand {
+            for target_id in &target_ids {
+                candidates.push(Action::Act(*card_instance, *target_id));
+            }
+        }
+
+        if let Ok(location) = self.find_character(character_id) {
+            for neighbor in
+                location.get_surrounding(self.board.grid.width(), self.board.grid.height())
+            {
+                candidates.push(Action::Move(character_id, neighbor));
+            }
 
-                    self.try_run_card_action(*actor, *target_id, Some(card_instance), &action);
+            for item_location in
+                self.board
+                    .grid
+                    .find_in_range(location.clone(), character.reach_distance(), |_| true)
+            {
+                match self.board.grid.get(item_location.x, item_location.y) {
+                    Some(BoardItem::Card(card_instance)) => candidates.push(Action::Take(
+                        character_id,
+                        item_location,
+                        TakeActionItem::Card(
+                            card_instance.card_id.id,
+                            card_instance.card_instance_id.id,
+                        ),
+                    )),
+                    Some(BoardItem::Character(other_id)) if *other_id != character_id => {
+                        for content in &self.characters[other_id].contains {
+                            candidates.push(Action::Take(
+                                character_id,
+                                item_location.clone(),
+                                match content {
+                                    Content::Card(card_instance) => TakeActionItem::Card(
+                                        card_instance.card_id.id,
+                                        card_instance.card_instance_id.id,
+                                    ),
+                                    Content::Object(object_instance) => TakeActionItem::Object(
+                                        object_instance.object_id.id,
+                                        object_instance.object_instance_id.id,
+                                    ),
+                                },
+                            ));
+                        }
+                    }
+                    _ => {}
                 }
+            }
+        }
+
+        let mut actions: Vec<Action> = candidates
+            .into_iter()
+            .filter(|action| self.validate_action(&character_id, action).is_ok())
+            .collect();
+        actions.push(Action::Pass);
+        actions
+    }
 
-                // Remove card from hand if still there after all effects ran
+    /// Attempts to carry out the action, returning why it was rejected if it was not legal. Actors
+    /// are not consulted, which lets AIs try out actions on a copy made with `clone_state`.
+    pub fn handle_action(
+        &mut self,
+        actor: &CharacterId,
+        action: Action,
+    ) -> Result<(), ActionRejection> {
+        self.validate_action(actor, &action)?;
+        match action {
+            Action::Pass => {
+                self.emit(BattleEvent::Passed {
+                    character_id: *actor,
+                });
                 let character = self.characters.require_mut(actor);
-                if character
-                    .hand
-                    .remove_first_match(|instance| instance == &card_instance)
-                    .is_some()
-                {
-                    character.discard.push(card_instance);
+                character.remaining_actions = 0;
+                character.movement = 0;
+            }
+            Action::Undo => {
+                self.undo_history
+                    .pop()
+                    .expect("Validated undo should have a snapshot")
+                    .restore(self);
+                self.emit(BattleEvent::ActionUndone {
+                    character_id: *actor,
+                });
+            }
+            Action::Move(target, location) => {
+                let from = self.find_character(target)?;
+                self.characters.require_mut(&target).movement -= 1;
+
+                self.board.grid.clear(from.x, from.y);
+
+                let prev_contents =
+                    self.board
+                        .grid
+                        .set(location.x, location.y, BoardItem::Character(target));
+                self.emit(BattleEvent::Moved {
+                    character_id: target,
+                    from,
+                    to: location,
+                });
+                match prev_contents {
+                    None => {}
+                    Some(BoardItem::Inert) => {
+                        panic!("Inert should not be in the way of movement");
+                    }
+                    Some(BoardItem::Card(card_instance)) => {
+                        self.characters
+                            .require_mut(&target)
+                            .hand
+                            .push(card_instance);
+                        self.emit(BattleEvent::ItemTaken {
+                            character_id: target,
+                            item: Content::Card(card_instance),
+                        });
+                    }
+                    Some(BoardItem::Character(_)) => {
+                        panic!("Character should not be in the way of movement");
+                    }
                 }
+                self.trigger_effects(target, target, Trigger::OnMove);
+            }
+            Action::Act(card_instance, target_id) => {
+                let card = &self.cards[&card_instance.card_id];
+                let (card_actions, cost) = (card.actions.clone(), card.cost);
+                self.emit(BattleEvent::CardPlayed {
+                    character_id: *actor,
+                    card: card_instance,
+                    target_id,
+                });
+
+                let character = self.characters.require_mut(actor);
+                character.remaining_actions -= 1;
+                character.energy -= cost;
+
+                self.card_in_play = Some(CardInPlay {
+                    actor: *actor,
+                    target_id,
+                    card_instance,
+                    actions: card_actions.into(),
+                });
+                self.resume_card();
+            }
+            Action::Take(_, location, item) => self.handle_take(actor, location, item),
+            Action::Choose(picked) => {
+                let choice = self
+                    .pending_choice
+                    .take()
+                    .expect("Validated choice should be pending");
+                self.apply_choice(choice, picked);
+                self.resume_card();
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the remaining actions of the card in play until it finishes or waits on a card choice
+    fn resume_card(&mut self) {
+        while self.pending_choice.is_none() {
+            let Some(card_in_play) = &mut self.card_in_play else {
+                return;
+            };
+            let (actor, target_id, card_instance) = (
+                card_in_play.actor,
+                card_in_play.target_id,
+                card_in_play.card_instance,
+            );
+            if let Some(action) = card_in_play.actions.pop_front() {
+                self.try_run_card_action(actor, target_id, Some(card_instance), &action);
+                continue;
+            }
+            self.card_in_play = None;
+
+            // Remove card from hand if still there after all effects ran
+            let character = self.characters.require_mut(&actor);
+            if character
+                .hand
+                .remove_first_match(|instance| instance == &card_instance)
+                .is_some()
+            {
+                character.discard.push(card_instance);
+            }
 
-                true
+            if !self.characters[&actor].is_dead() {
+                self.trigger_effects(actor, target_id, Trigger::OnCardPlayed);
             }
-            Action::Take(character_id, location, item) => self
-                .handle_take(actor, character_id, location, item)
-                .unwrap_or_default(),
         }
     }
 
-    fn handle_take(
+    /// Has the character pick cards from their hand, or from the top of their deck to reorder.
+    /// Only the actor of a card in play is asked to choose; effects and battles without actors,
+    /// such as the copies AIs simulate on, take the default pick straight away.
+    fn offer_choice(
         &mut self,
-        actor: &CharacterId,
         character_id: CharacterId,
-        location: GridLocation,
-        item: TakeActionItem,
-    ) -> Option<bool> {
-        if character_id != *actor {
-            // Currently only the actor can take for themself
-            return None;
+        kind: CardChoiceKind,
+        amount: &U64Range,
+        random: bool,
+        card_instance: Option<CardInstance>,
+    ) {
+        let count = amount.resolve(self.random_provider.as_ref()) as usize;
+        let character = &self.characters[&character_id];
+        if character.is_dead() {
+            return;
+        }
+        let cards: Vec<CardInstance> = match kind {
+            CardChoiceKind::Reorder => character.deck.iter().take(count).copied().collect(),
+            // The card being played cannot discard or exhaust itself
+            _ => character
+                .hand
+                .iter()
+                .filter(|instance| Some(**instance) != card_instance)
+                .copied()
+                .collect(),
+        };
+        let count = count.min(cards.len());
+        if count == 0 {
+            return;
         }
 
-        let (item_id, item_instance_id) = item.id();
+        let choice = CardChoice {
+            character_id,
+            kind,
+            cards,
+            count,
+        };
+        if random {
+            let picked = choice
+                .cards
+                .pick_n_unique_linear(count, self.random_provider.as_ref())
+                .into_iter()
+                .copied()
+                .collect();
+            self.apply_choice(choice, picked);
+        } else if card_instance.is_some() && !self.actors.is_empty() {
+            self.pending_choice = Some(choice);
+        } else {
+            let picked = choice.default_pick();
+            self.apply_choice(choice, picked);
+        }
+    }
+
+    fn apply_choice(&mut self, choice: CardChoice, picked: Vec<CardInstance>) {
+        let character_id = choice.character_id;
+        let count = picked.len() as u64;
+        let character = self.characters.require_mut(&character_id);
+        match choice.kind {
+            CardChoiceKind::Discard => {
+                for card in picked {
+                    if character
+                        .hand
+                        .remove_first_match(|instance| *instance == card)
+                        .is_some()
+                    {
+                        character.discard.push(card);
+                    }
+                }
+                self.emit(BattleEvent::CardsDiscarded {
+                    character_id,
+                    count,
+                });
+            }
+            CardChoiceKind::Exhaust => {
+                for card in picked {
+                    character
+                        .hand
+                        .remove_first_match(|instance| *instance == card);
+                }
+                self.emit(BattleEvent::CardsExhausted {
+                    character_id,
+                    count,
+                });
+            }
+            CardChoiceKind::Reorder => {
+                character.deck.splice(..picked.len(), picked);
+                self.emit(BattleEvent::DeckReordered {
+                    character_id,
+                    count,
+                });
+            }
+        }
+    }
+
+    /// An instance id no card in the battle has yet
+    fn next_card_instance_id(&self) -> CardInstanceId {
+        let characters = self.characters.values().flat_map(|character| {
+            character
+                .hand
+                .iter()
+                .chain(&character.deck)
+                .chain(&character.discard)
+                .copied()
+                .chain(
+                    character
+                        .contains
+                        .iter()
+                        .filter_map(|content| match content {
+                            Content::Card(instance) => Some(*instance),
+                            Content::Object(_) => None,
+                        }),
+                )
+        });
+        let board = self.board.grid.iter().filter_map(|item| match item {
+            BoardItem::Card(instance) => Some(*instance),
+            _ => None,
+        });
+        CardInstanceId::new(
+            characters
+                .chain(board)
+                .map(|instance| instance.card_instance_id.id + 1)
+                .max()
+                .unwrap_or(0),
+        )
+    }
 
-        let (x, y) = self.board.find(&BoardItem::Character(character_id))?;
-        let character = &self.characters[actor];
-        if location.distance(&GridLocation { x, y }) > character.reach_distance() {
-            // If the character is not in range, they cannot take anything
-            return None;
+    /// Asks for the card choices the last action left open until none remain. The choices of the
+    /// character `fallback_actor` plays for go to it rather than to the character's own actor.
+    async fn resolve_choices(&mut self, fallback_actor: Option<&dyn Actor>) {
+        while let Some(choice) = self.pending_choice.clone() {
+            let actor: &dyn Actor = match fallback_actor {
+                Some(actor) if *actor.get_character_id() == choice.character_id => actor,
+                _ => self.require_actor(&choice.character_id),
+            };
+            let picked = match self.turn_deadline {
+                Some(deadline) => timeout_at(deadline.into(), actor.choose_cards(self, &choice))
+                    .await
+                    .unwrap_or_else(|_| choice.default_pick()),
+                None => actor.choose_cards(self, &choice).await,
+            };
+            let picked = if choice.is_valid(&picked) {
+                picked
+            } else {
+                choice.default_pick()
+            };
+            let action = Action::Choose(picked);
+            let result = self.handle_action(&choice.character_id, action.clone());
+            let entry = ActionLogEntry {
+                round: self.round,
+                character_id: choice.character_id,
+                action,
+                accepted: result.is_ok(),
+            };
+            self.notify_observers(|observer, battle| observer.on_action(battle, &entry));
+            self.action_log.push(entry);
         }
+    }
+
+    /// Moves a validated item into the actor's hand or contents
+    fn handle_take(&mut self, actor: &CharacterId, location: GridLocation, item: TakeActionItem) {
+        let (item_id, _) = item.id();
 
         let add_card = |battle: &mut Battle, card_instance: CardInstance| {
-            let character = battle.characters.get_mut(actor).unwrap();
-            battle.history.push(battle_markup![
-                @id(&character.name),
-                " took ",
-                @id(&battle.cards[&card_instance.card_id].name),
-                ". "
-            ]);
-            character.hand.push(card_instance);
+            battle
+                .characters
+                .require_mut(actor)
+                .hand
+                .push(card_instance);
+            battle.emit(BattleEvent::ItemTaken {
+                character_id: *actor,
+                item: Content::Card(card_instance),
+            });
         };
         let add_object = |battle: &mut Battle, object_instance: ObjectInstance| {
-            let character: &mut Character = battle.characters.get_mut(actor).unwrap();
-            battle.history.push(battle_markup![
-                @id(&character.name),
-                " took ",
-                @id(&battle.objects[&object_instance.object_id].name),
-                ". "
-            ]);
-            character.contains.push(Content::Object(object_instance));
+            battle
+                .characters
+                .require_mut(actor)
+                .contains
+                .push(Content::Object(object_instance));
+            battle.emit(BattleEvent::ItemTaken {
+                character_id: *actor,
+                item: Content::Object(object_instance),
+            });
         };
 
-        let board_item = self.board.grid.get(location.x, location.y)?;
-        match board_item {
-            &BoardItem::Inert => {
-                // If the item is inert, they cannot take anything
-                None
-            }
-            &BoardItem::Card(card_instance) => {
-                if card_instance
-                    != CardInstance::new(
-                        CardId::new(*item_id),
-                        CardInstanceId::new(*item_instance_id),
-                    )
-                {
-                    // Not the right card
-
-                    return None;
-                }
-                self.board.grid.clear(x, y);
+        match self.board.grid.get(location.x, location.y) {
+            Some(&BoardItem::Card(card_instance)) => {
+                self.board.grid.clear(location.x, location.y);
                 add_card(self, card_instance);
-                Some(true)
             }
-            BoardItem::Character(loc_character_id) => {
+            Some(&BoardItem::Character(loc_character_id)) => {
                 let content = self
                     .characters
-                    .require_mut(loc_character_id)
+                    .require_mut(&loc_character_id)
                     .contains
-                    .remove_first_match(|content| match content {
-                        Content::Object(instance) => &instance.object_id.id == item_id,
-                        Content::Card(instance) => &instance.card_id.id == item_id,
-                    })?;
+                    .remove_first_match(|content| content_matches(content, *item_id))
+                    .expect("Validated item should be present");
 
                 match content {
                     Content::Object(object_instance) => {
@@ -404,28 +966,32 @@ impl Battle {
                         }
                     }
                 }
-                Some(true)
             }
+            None | Some(BoardItem::Inert) => panic!("Validated item should be present"),
         }
     }
 
     pub async fn advance(&mut self) -> Result<(), ExitCode> {
         self.round += 1;
-        self.history
-            .push(battle_markup![format!("--- Round {}", self.round)]);
+        self.notify_observers(|observer, battle| observer.on_round_start(battle));
+        self.emit(BattleEvent::RoundStarted { round: self.round });
+        self.trigger_round_effects(Trigger::RoundStart);
         let turns = self.build_turns();
-        for turn in turns {
+        'turns: for turn in turns {
             if self.characters[&turn.character].is_dead() {
                 continue;
             }
-            for effect_id in self.characters[&turn.character].effects.clone() {
-                self.try_run_effect(
-                    turn.character,
-                    turn.character,
-                    effect_id,
-                    Trigger::TurnStart,
-                );
+            self.notify_observers(|observer, battle| {
+                observer.on_turn_start(battle, turn.character)
+            });
+            // Block only lasts until the character's next turn
+            let block = self.characters[&turn.character].block;
+            if block > 0 {
+                self.lose_defense(turn.character, Defense::Block, block);
             }
+            let energy_per_turn = self.characters[&turn.character].energy_per_turn;
+            self.gain_energy(turn.character, energy_per_turn);
+            self.trigger_effects(turn.character, turn.character, Trigger::TurnStart);
 
             let character = self.characters.require_mut(&turn.character);
 
@@ -434,18 +1000,90 @@ impl Battle {
                 .get_default_turn_actions()
                 .unwrap_or(self.default_turn_actions);
             character.movement = character.default_movement;
+            self.undo_history.clear();
+
+            let timer = self.turn_timers.get(&turn.character).cloned();
+            self.turn_deadline = timer.as_ref().map(|timer| Instant::now() + timer.limit);
+            // Plays the rest of the turn once the character's own actor runs out of time
+            let mut fallback_actor: Option<Box<dyn Actor>> = None;
 
             while self.characters[&turn.character].remaining_actions > 0
                 || self.characters[&turn.character].movement > 0
             {
-                let actor: &dyn Actor = self.require_actor(&turn.character);
-                let action_result = actor.act(self).await;
+                let action_result = if let Some(fallback_actor) = &fallback_actor {
+                    fallback_actor.act(self).await
+                } else if let Some(deadline) = self.turn_deadline {
+                    let actor: &dyn Actor = self.require_actor(&turn.character);
+                    match timeout_at(deadline.into(), actor.act(self)).await {
+                        Ok(action_result) => action_result,
+                        Err(_) => {
+                            self.turn_deadline = None;
+                            self.emit(BattleEvent::TurnTimedOut {
+                                character_id: turn.character,
+                            });
+                            match timer.as_ref().map(|timer| timer.on_timeout) {
+                                Some(TurnTimeout::Ai(personality)) => {
+                                    let actor = create_ai_actor(turn.character, personality);
+                                    let action_result = actor.act(self).await;
+                                    fallback_actor = Some(actor);
+                                    action_result
+                                }
+                                _ => Ok(Action::Pass),
+                            }
+                        }
+                    }
+                } else {
+                    let actor: &dyn Actor = self.require_actor(&turn.character);
+                    actor.act(self).await
+                };
                 match action_result {
                     Ok(request) => {
-                        self.handle_action(&turn.character, request);
-                        if let Some(end_state) = self.did_battle_reach_end_condition() {
-                            self.end_state = Some(end_state);
-                            return Ok(());
+                        let snapshot = (request != Action::Undo
+                            && self.players.contains(&turn.character))
+                        .then(|| UndoSnapshot::new(self));
+                        let random_picks = self.random_picks.load(Ordering::Relaxed);
+                        let event_count = self.events.len();
+                        let result = self.handle_action(&turn.character, request.clone());
+                        let entry = ActionLogEntry {
+                            round: self.round,
+                            character_id: turn.character,
+                            action: request,
+                            accepted: result.is_ok(),
+                        };
+                        self.notify_observers(|observer, battle| {
+                            observer.on_action(battle, &entry)
+                        });
+                        if let Err(rejection) = &result {
+                            self.require_actor(&turn.character)
+                                .on_action_rejected(self, &entry.action, rejection)
+                                .await;
+                        }
+                        self.action_log.push(entry);
+                        self.resolve_choices(fallback_actor.as_deref()).await;
+                        if let (Ok(()), Some(snapshot)) = (&result, snapshot) {
+                            // Drawn cards and the top of the deck cannot be hidden again either
+                            let revealed_random = self.random_picks.load(Ordering::Relaxed)
+                                != random_picks
+                                || self.events[event_count..].iter().any(|event| {
+                                    matches!(
+                                        event,
+                                        BattleEvent::CardsDrawn { .. }
+                                            | BattleEvent::DeckReordered { .. }
+                                    )
+                                });
+                            self.undo_history.push(snapshot, revealed_random);
+                        }
+
+                        let end_state = self.did_battle_reach_end_condition();
+                        self.notify_observers(|observer, battle| {
+                            observer.on_end_condition_evaluated(battle, end_state)
+                        });
+                        if end_state.is_some() {
+                            self.end_state = end_state;
+                            self.notify_observers(|observer, battle| {
+                                observer.on_turn_end(battle, turn.character)
+                            });
+                            break 'turns;
                         }
                     }
                     Err(ActionError::Failure(failure)) => {
@@ -456,7 +1094,18 @@ impl Battle {
                     }
                 }
             }
+            self.turn_deadline = None;
+            self.undo_history.clear();
+            if !self.characters[&turn.character].is_dead() {
+                self.trigger_effects(turn.character, turn.character, Trigger::TurnEnd);
+                self.expire_effects(turn.character);
+            }
+            self.notify_observers(|observer, battle| observer.on_turn_end(battle, turn.character));
+        }
+        if self.end_state.is_none() {
+            self.trigger_round_effects(Trigger::RoundEnd);
         }
+        self.notify_observers(|observer, battle| observer.on_round_end(battle));
         Ok(())
     }
 
@@ -464,15 +1113,9 @@ impl Battle {
         while self.end_state.is_none() {
             self.advance().await?;
         }
-        let history_line: BattleText = match self.end_state.unwrap() {
-            EndConditionType::Win => {
-                battle_markup!["Congratulations, you won."]
-            }
-            EndConditionType::Loss => {
-                battle_markup!["Sorry, you lost."]
-            }
-        };
-        self.history.push(history_line);
+        self.emit(BattleEvent::BattleEnded {
+            end_state: self.end_state.unwrap(),
+        });
 
         for (_, actor) in &self.actors {
             actor.on_game_over(self).await;
@@ -487,21 +1130,23 @@ impl Battle {
         card_instance: Option<CardInstance>,
         action: &CardAction,
     ) -> bool {
-        let mut history_entry = vec![];
-
         // If the action specifically targets me, then force it to target the actor
         // rather than the potentially other target.
         let target_id = if action.target() == &Target::Me {
-            &actor
+            actor
         } else {
-            &target_id
+            target_id
         };
 
-        let target_character = self.characters.require_mut(target_id);
         match action {
-            CardAction::Damage { amount, area, .. } => {
+            CardAction::Damage {
+                amount,
+                area,
+                damage_type,
+                ..
+            } => {
                 for (attacked_character_id, value) in
-                    self.get_all_character_amounts_in_range(*target_id, area, amount)
+                    self.get_all_character_amounts_in_range(target_id, area, amount)
                 {
                     let attacked_character = self.characters.require_mut(&attacked_character_id);
 
@@ -509,18 +1154,31 @@ impl Battle {
                         continue;
                     }
 
-                    history_entry.extend(battle_markup![@damage(&value), " damage to ", @id(&attacked_character.name), ". " ]);
-                    attacked_character.health -= Attack::new(value);
+                    let value = attacked_character.resist(*damage_type, value);
+                    let taken = attacked_character.take_damage(Attack::new(value));
+                    let died = attacked_character.is_dead();
+                    self.emit(BattleEvent::DamageDealt {
+                        source_id: actor,
+                        target_id: attacked_character_id,
+                        amount: taken.dealt,
+                        blocked: taken.blocked,
+                        damage_type: *damage_type,
+                        card_id: card_instance.map(|instance| instance.card_id),
+                    });
 
-                    if attacked_character.is_dead() {
-                        for effect_id in attacked_character.effects.clone() {
-                            self.try_run_effect(
-                                attacked_character_id,
-                                attacked_character_id,
-                                effect_id,
-                                Trigger::Death,
-                            );
+                    if !died {
+                        if taken.dealt > 0 {
+                            self.trigger_effects(attacked_character_id, actor, Trigger::OnDamaged);
                         }
+                    } else {
+                        self.emit(BattleEvent::CharacterDied {
+                            character_id: attacked_character_id,
+                        });
+                        self.trigger_effects(
+                            attacked_character_id,
+                            attacked_character_id,
+                            Trigger::Death,
+                        );
 
                         // Remove the character from the board
                         if let Some((x, y)) = self
@@ -534,49 +1192,50 @@ impl Battle {
             }
             CardAction::Heal { amount, area, .. } => {
                 for (healed_character_id, value) in
-                    self.get_all_character_amounts_in_range(*target_id, area, amount)
+                    self.get_all_character_amounts_in_range(target_id, area, amount)
                 {
-                    let healed_character = self.characters.require_mut(&healed_character_id);
-
-                    history_entry.extend(battle_markup!["Healed ", @damage(&value), ". "]);
-
-                    healed_character.heal(Health::new(value));
+                    self.characters
+                        .require_mut(&healed_character_id)
+                        .heal(Health::new(value));
+                    self.emit(BattleEvent::Healed {
+                        source_id: actor,
+                        target_id: healed_character_id,
+                        amount: value,
+                    });
+                    self.trigger_effects(healed_character_id, actor, Trigger::OnHeal);
                 }
             }
             CardAction::GainAction { amount, .. } => {
                 let value = amount.resolve(self.random_provider.as_ref());
-                history_entry.extend(battle_markup![format!(
-                    "Gained {} action{}. ",
-                    value,
-                    if value != 1 { "s" } else { "" }
-                )]);
-                target_character.remaining_actions += value;
+                self.characters.require_mut(&target_id).remaining_actions += value;
+                self.emit(BattleEvent::ActionsGained {
+                    character_id: target_id,
+                    amount: value,
+                });
             }
             CardAction::Move { amount, .. } => {
                 let value = amount.resolve(self.random_provider.as_ref());
-                history_entry.extend(battle_markup![format!("Moved {} spaces. ", value)]);
-                target_character.movement += value;
+                self.characters.require_mut(&target_id).movement += value;
+                self.emit(BattleEvent::MovementGained {
+                    character_id: target_id,
+                    amount: value,
+                });
             }
             CardAction::Effect { effect, chance, .. } => {
                 if chance.resolve(self.random_provider.as_ref()) {
-                    history_entry.extend(battle_markup![
-                        @id(&target_character.name),
-                        " got ",
-                        @id(&self.effects[effect].name),
-                    ]);
-                    target_character.effects.push(*effect);
+                    self.apply_effect(target_id, *effect, Some(actor));
                 }
             }
             CardAction::RemoveEffect { effect, chance, .. } => {
                 if chance.resolve(self.random_provider.as_ref())
-                    && target_character.effects.contains(effect)
+                    && self.characters[&target_id].effect_stacks(*effect) > 0
                 {
-                    history_entry.extend(battle_markup![
-                        @id(&target_character.name),
-                        " lost ",
-                        @id(&self.effects[effect].name),
-                    ]);
-                    target_character.effects.retain(|e| e != effect);
+                    let count = self.remove_effect(target_id, *effect, u64::MAX);
+                    self.emit(BattleEvent::EffectRemoved {
+                        character_id: target_id,
+                        effect_id: *effect,
+                        count,
+                    });
                 }
             }
             CardAction::ReduceEffect {
@@ -586,72 +1245,411 @@ impl Battle {
                 ..
             } => {
                 if chance.resolve(self.random_provider.as_ref())
-                    && target_character.effects.contains(effect)
+                    && self.characters[&target_id].effect_stacks(*effect) > 0
                 {
-                    history_entry.extend(battle_markup![
-                        @id(&target_character.name),
-                        format!(" lost up to {} ", amount),
-                        @id(&self.effects[effect].name),
-                    ]);
-                    let mut amount = *amount;
-                    target_character.effects.retain(|e| {
-                        if e != effect {
-                            return true;
-                        }
-
-                        if amount == 0 {
-                            return true;
-                        }
-
-                        amount -= 1;
-                        false
+                    let count = self.remove_effect(target_id, *effect, *amount);
+                    self.emit(BattleEvent::EffectRemoved {
+                        character_id: target_id,
+                        effect_id: *effect,
+                        count,
+                    });
+                }
+            }
+            CardAction::GainDefense {
+                defense, amount, ..
+            } => {
+                let value = amount.resolve(self.random_provider.as_ref());
+                *self
+                    .characters
+                    .require_mut(&target_id)
+                    .defense_mut(*defense) += value;
+                self.emit(BattleEvent::DefenseGained {
+                    character_id: target_id,
+                    defense: *defense,
+                    amount: value,
+                });
+            }
+            CardAction::StripDefense {
+                defense, amount, ..
+            } => {
+                let current = self.characters[&target_id].defense(*defense);
+                let value = amount
+                    .as_ref()
+                    .map_or(current, |amount| {
+                        amount.resolve(self.random_provider.as_ref())
+                    })
+                    .min(current);
+                if value > 0 {
+                    self.lose_defense(target_id, *defense, value);
+                }
+            }
+            CardAction::GainEnergy { amount, .. } => {
+                let value = amount.resolve(self.random_provider.as_ref());
+                self.gain_energy(target_id, value);
+            }
+            CardAction::LoseEnergy { amount, .. } => {
+                let value = amount
+                    .resolve(self.random_provider.as_ref())
+                    .min(self.characters[&target_id].energy);
+                if value > 0 {
+                    self.characters.require_mut(&target_id).energy -= value;
+                    self.emit(BattleEvent::EnergyLost {
+                        character_id: target_id,
+                        amount: value,
+                    });
+                }
+            }
+            CardAction::Draw { amount, .. } => {
+                let count = amount.resolve(self.random_provider.as_ref()) as usize;
+                let drawn = self
+                    .characters
+                    .require_mut(&target_id)
+                    .draw(count, self.random_provider.as_ref());
+                if drawn > 0 {
+                    self.emit(BattleEvent::CardsDrawn {
+                        character_id: target_id,
+                        count: drawn as u64,
                     });
                 }
             }
+            CardAction::Discard { amount, random, .. } => self.offer_choice(
+                target_id,
+                CardChoiceKind::Discard,
+                amount,
+                *random,
+                card_instance,
+            ),
+            CardAction::Exhaust { amount, random, .. } => self.offer_choice(
+                target_id,
+                CardChoiceKind::Exhaust,
+                amount,
+                *random,
+                card_instance,
+            ),
+            CardAction::ReorderDeck { amount, .. } => self.offer_choice(
+                target_id,
+                CardChoiceKind::Reorder,
+                amount,
+                false,
+                card_instance,
+            ),
+            CardAction::AddCard { card, pile, .. } => {
+                let card = CardInstance::new(*card, self.next_card_instance_id());
+                let random_provider = self.random_provider.as_ref();
+                let character = self.characters.require_mut(&target_id);
+                match pile {
+                    Pile::Hand => character.hand.push(card),
+                    Pile::Deck => {
+                        let index = random_provider.pick_linear_usize(0, character.deck.len());
+                        character.deck.insert(index, card);
+                    }
+                    Pile::Discard => character.discard.push(card),
+                }
+                self.emit(BattleEvent::CardAdded {
+                    character_id: target_id,
+                    card,
+                    pile: *pile,
+                });
+            }
+            CardAction::ShuffleDiscard { .. } => {
+                self.characters
+                    .require_mut(&target_id)
+                    .shuffle_discard(self.random_provider.as_ref());
+                self.emit(BattleEvent::DiscardShuffled {
+                    character_id: target_id,
+                });
+            }
             CardAction::DestroySelf { chance } => {
                 if let Some(card_instance) = card_instance
                     && chance.resolve(self.random_provider.as_ref())
-                    && target_character
+                    && self
+                        .characters
+                        .require_mut(&target_id)
                         .hand
                         .remove_first_match(|ci| ci == &card_instance)
                         .is_some()
                 {
-                    history_entry.extend(battle_markup![
-                        @id(&self.cards[&card_instance.card_id].name),
-                        " was destroyed.",
-                    ]);
+                    self.emit(BattleEvent::CardDestroyed {
+                        character_id: target_id,
+                        card: card_instance,
+                    });
                 }
             }
         }
 
-        if !history_entry.is_empty() {
-            self.history.push(history_entry);
+        true
+    }
+
+    fn gain_energy(&mut self, character_id: CharacterId, amount: u64) {
+        let gained = self
+            .characters
+            .require_mut(&character_id)
+            .gain_energy(amount);
+        if gained > 0 {
+            self.emit(BattleEvent::EnergyGained {
+                character_id,
+                amount: gained,
+            });
         }
+    }
 
-        true
+    fn lose_defense(&mut self, character_id: CharacterId, defense: Defense, amount: u64) {
+        *self
+            .characters
+            .require_mut(&character_id)
+            .defense_mut(defense) -= amount;
+        self.emit(BattleEvent::DefenseLost {
+            character_id,
+            defense,
+            amount,
+        });
     }
 
-    fn try_run_effect(
+    /// Applies the effect to the character following the effect's stacking
+    fn apply_effect(
         &mut self,
-        actor: CharacterId,
-        target_id: CharacterId,
+        character_id: CharacterId,
         effect_id: EffectId,
-        trigger: Trigger,
+        source: Option<CharacterId>,
     ) {
         let effect = &self.effects[&effect_id];
-        if !effect.has_trigger(trigger) {
-            return;
+        if self
+            .characters
+            .require_mut(&character_id)
+            .add_effect(effect, source)
+        {
+            self.emit(BattleEvent::EffectApplied {
+                character_id,
+                effect_id,
+            });
+        }
+    }
+
+    /// Takes up to `count` stacks off the effect and returns how many were
+    fn remove_effect(&mut self, character_id: CharacterId, effect_id: EffectId, count: u64) -> u64 {
+        self.characters
+            .require_mut(&character_id)
+            .reduce_effect(effect_id, count)
+    }
+
+    /// Counts down the character's effects with a duration at the end of its turn and removes
+    /// those that ran out
+    fn expire_effects(&mut self, character_id: CharacterId) {
+        let character = self.characters.require_mut(&character_id);
+        let mut expired = vec![];
+        character.effects.retain_mut(|instance| {
+            let Some(remaining_turns) = &mut instance.remaining_turns else {
+                return true;
+            };
+            *remaining_turns = remaining_turns.saturating_sub(1);
+            if *remaining_turns == 0 {
+                expired.push(instance.effect_id);
+            }
+            *remaining_turns > 0
+        });
+
+        for effect_id in expired {
+            self.emit(BattleEvent::EffectExpired {
+                character_id,
+                effect_id,
+            });
+        }
+    }
+
+    /// Runs the character's effects with the trigger. Their actions that target others target
+    /// `target_id`.
+    fn trigger_effects(
+        &mut self,
+        character_id: CharacterId,
+        target_id: CharacterId,
+        trigger: Trigger,
+    ) {
+        if trigger.is_reaction() {
+            if self.reacting {
+                return;
+            }
+            self.reacting = true;
+        }
+        for instance in self.characters[&character_id].effects.clone() {
+            self.try_run_effect(character_id, target_id, &instance, trigger);
+        }
+        if trigger.is_reaction() {
+            self.reacting = false;
+        }
+    }
+
+    /// Runs the effects with the trigger of every living character, in the order of their actors
+    fn trigger_round_effects(&mut self, trigger: Trigger) {
+        let character_ids: Vec<CharacterId> = self
+            .actors
+            .iter()
+            .map(|(_, actor)| *actor.get_character_id())
+            .collect();
+        for character_id in character_ids {
+            if !self.characters[&character_id].is_dead() {
+                self.trigger_effects(character_id, character_id, trigger);
+            }
+        }
+    }
+
+    fn try_run_effect(
+        &mut self,
+        actor: CharacterId,
+        target_id: CharacterId,
+        instance: &EffectInstance,
+        trigger: Trigger,
+    ) {
+        let effect = &self.effects[&instance.effect_id];
+        if !effect.has_trigger(trigger) {
+            return;
         }
 
         for action in effect.actions.clone() {
-            self.try_run_card_action(actor, target_id, None, &action);
+            match action {
+                // Every stack rolls its own chance to wear off, as separate applications would
+                CardAction::ReduceEffect { .. } => {
+                    for _ in 0..instance.stacks {
+                        self.try_run_card_action(actor, target_id, None, &action);
+                    }
+                }
+                _ => {
+                    self.try_run_card_action(
+                        actor,
+                        target_id,
+                        None,
+                        &action.scaled(instance.stacks),
+                    );
+                }
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Battle, BoardItem, CardId, CardInstance, CardInstanceId, DefaultRandomProvider};
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use crate::{
+        Action, ActionRejection, ActionResult, Actor, Battle, BattleEvent, BoardItem, CardAction,
+        CardChoice, CardChoiceKind, CardId, CardInstance, CardInstanceId, CharacterId, DamageType,
+        DefaultRandomProvider, Defense, DumbActor, EffectId, EffectInstance, GridLocation,
+        HashMapExt, Health, Pile, RandomPick, RecordingRandomProvider, ReplayActor,
+        ReplayRandomProvider, SeededRandomProvider, Target, Trigger, U64Range, UndoSnapshot,
+    };
+
+    /// A battle with no player so every character is controlled by an AI and runs unattended.
+    const AI_ONLY_BATTLE: &str = r#"{
+        "title": "AI Battle",
+        "description": "No humans allowed",
+        "default_hand_size": 2,
+        "default_movement": 1,
+        "board": { "width": 4, "height": 4 },
+        "effects": [
+            {
+                "id": 0,
+                "name": "Poison",
+                "description": "Deals damage over time",
+                "image": "poison.png",
+                "triggers": ["turn_start"],
+                "actions": [
+                    { "type": "damage", "target": "self", "amount": 1 },
+                    { "type": "reduce_effect", "target": "self", "effect": 0, "chance": 0.5 }
+                ]
+            }
+        ],
+        "cards": [
+            {
+                "id": 0,
+                "name": "Slash",
+                "description": "Deal 1-4 damage",
+                "range": 1,
+                "actions": [{ "type": "damage", "target": "others", "amount": [1, 4] }]
+            },
+            {
+                "id": 1,
+                "name": "Poison Dart",
+                "description": "Maybe poison",
+                "range": 3,
+                "actions": [
+                    { "type": "damage", "target": "others", "amount": [0, 2] },
+                    { "type": "effect", "target": "others", "effect": 0, "chance": 0.5 }
+                ]
+            }
+        ],
+        "end_conditions": [
+            {
+                "title": "Victory",
+                "description": "Team B is defeated",
+                "type": "win",
+                "condition": { "type": "team_member_death", "ids": [2] }
+            },
+            {
+                "title": "Defeat",
+                "description": "Team A is defeated",
+                "type": "loss",
+                "condition": { "type": "team_member_death", "ids": [0, 1] }
+            }
+        ],
+        "teams": [
+            {
+                "name": "Team A",
+                "members": [
+                    {
+                        "name": "A1",
+                        "race": "Human",
+                        "base_health": 8,
+                        "cards": [0, 0, 1],
+                        "location": [0, 0],
+                        "image": "member.png"
+                    },
+                    {
+                        "name": "A2",
+                        "race": "Machine",
+                        "base_health": 6,
+                        "cards": [1, 1, 0],
+                        "location": [0, 3],
+                        "image": "member.png"
+                    }
+                ]
+            },
+            {
+                "name": "Team B",
+                "members": [
+                    {
+                        "name": "B1",
+                        "race": "Human",
+                        "base_health": 15,
+                        "cards": [0, 1, 0, 1],
+                        "location": [3, 2],
+                        "image": "member.png"
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    /// `AI_ONLY_BATTLE` with `change` made to its JSON, for tests that need more of the battle file
+    fn ai_only_battle_with(change: impl FnOnce(&mut serde_json::Value)) -> String {
+        let mut data: serde_json::Value = serde_json::from_str(AI_ONLY_BATTLE).unwrap();
+        change(&mut data);
+        data.to_string()
+    }
+
+    /// The team members of battle file JSON, in the order of their character ids
+    fn members(data: &mut serde_json::Value) -> Vec<&mut serde_json::Value> {
+        data["teams"]
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .flat_map(|team| team["members"].as_array_mut().unwrap())
+            .collect()
+    }
+
+    fn history_json(battle: &Battle) -> String {
+        serde_json::to_string(&battle.history).unwrap()
+    }
 
     #[tokio::test]
     async fn test_deserialize() -> Result<(), String> {
@@ -740,8 +1738,13 @@ mod tests {
                 }
             ]
         }"#;
-        let mut battle =
-            Battle::deserialize(battle_json, None, Box::<DefaultRandomProvider>::default()).await?;
+        let mut battle = Battle::deserialize(
+            battle_json,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+            Default::default(),
+        )
+        .await?;
         assert_eq!(battle.history.len(), 0);
         assert_eq!(battle.teams.len(), 2);
         assert_eq!(battle.teams[0].name, "Team A".to_string());
@@ -785,4 +1788,1017 @@ mod tests {
         battle.run_to_completion().await.unwrap();
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_seeded_battle_is_reproducible() -> Result<(), String> {
+        let mut first = Battle::deserialize(
+            AI_ONLY_BATTLE,
+            None,
+            Box::new(SeededRandomProvider::new(42)),
+            Default::default(),
+        )
+        .await?;
+        let mut second = Battle::deserialize(
+            AI_ONLY_BATTLE,
+            None,
+            Box::new(SeededRandomProvider::new(42)),
+            Default::default(),
+        )
+        .await?;
+
+        first.run_to_completion().await.unwrap();
+        second.run_to_completion().await.unwrap();
+
+        assert!(first.end_state.is_some());
+        assert_eq!(first.end_state, second.end_state);
+        assert_eq!(first.round, second.round);
+        assert_eq!(history_json(&first), history_json(&second));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recorded_battle_replays() -> Result<(), String> {
+        let recorder = RecordingRandomProvider::new(Box::new(SeededRandomProvider::new(7)));
+        let log = recorder.log();
+        let mut recorded =
+            Battle::deserialize(AI_ONLY_BATTLE, None, Box::new(recorder), Default::default())
+                .await?;
+        recorded.run_to_completion().await.unwrap();
+
+        let picks = log.lock().unwrap().clone();
+        assert!(!picks.is_empty());
+        let mut replayed = Battle::deserialize(
+            AI_ONLY_BATTLE,
+            None,
+            Box::new(ReplayRandomProvider::new(picks)),
+            Default::default(),
+        )
+        .await?;
+        replayed.run_to_completion().await.unwrap();
+
+        assert_eq!(recorded.end_state, replayed.end_state);
+        assert_eq!(history_json(&recorded), history_json(&replayed));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_history_is_rendered_from_events() -> Result<(), String> {
+        let mut battle = Battle::deserialize(
+            AI_ONLY_BATTLE,
+            None,
+            Box::new(SeededRandomProvider::new(3)),
+            Default::default(),
+        )
+        .await?;
+        battle.run_to_completion().await.unwrap();
+
+        assert_eq!(
+            battle.events.first(),
+            Some(&BattleEvent::RoundStarted { round: 1 })
+        );
+        assert_eq!(
+            battle.events.last(),
+            Some(&BattleEvent::BattleEnded {
+                end_state: battle.end_state.unwrap()
+            })
+        );
+        for character in battle.characters.values() {
+            assert_eq!(
+                character.is_dead(),
+                battle.events.contains(&BattleEvent::CharacterDied {
+                    character_id: character.id
+                })
+            );
+        }
+
+        let rendered: Vec<_> = battle
+            .events
+            .iter()
+            .filter_map(|event| event.to_battle_text(&battle))
+            .collect();
+        assert_eq!(
+            serde_json::to_string(&rendered).unwrap(),
+            history_json(&battle)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_action_rejections() -> Result<(), String> {
+        let mut battle = Battle::deserialize_with_actors(
+            AI_ONLY_BATTLE,
+            None,
+            Box::new(SeededRandomProvider::new(1)),
+            |id, _| Box::new(DumbActor { character_id: id }),
+        )?;
+        let a1 = CharacterId::new(0);
+        let b1 = CharacterId::new(2);
+        let slash = *battle.characters[&a1]
+            .deck
+            .iter()
+            .find(|card| card.card_id == CardId::new(0))
+            .unwrap();
+        let act = Action::Act(slash, b1);
+
+        assert_eq!(
+            battle.validate_action(&a1, &act),
+            Err(ActionRejection::CardNotInHand { card: slash })
+        );
+        battle.characters.get_mut(&a1).unwrap().hand.push(slash);
+        assert_eq!(
+            battle.validate_action(&a1, &act),
+            Err(ActionRejection::NoActionsRemaining)
+        );
+        battle.characters.get_mut(&a1).unwrap().remaining_actions = 1;
+        let rejection = battle.validate_action(&a1, &act).unwrap_err();
+        assert_eq!(
+            rejection,
+            ActionRejection::OutOfRange {
+                distance: 5,
+                range: 1
+            }
+        );
+        assert_eq!(rejection.to_string(), "Target out of range (5 > 1)");
+        assert_eq!(
+            battle.validate_action(&a1, &Action::Act(slash, a1)),
+            Err(ActionRejection::WrongTarget {
+                expected: Target::Others
+            })
+        );
+
+        let step = Action::Move(a1, GridLocation { x: 1, y: 0 });
+        assert_eq!(
+            battle.validate_action(&a1, &step),
+            Err(ActionRejection::NoMovementRemaining)
+        );
+        battle.characters.get_mut(&a1).unwrap().movement = 1;
+        assert_eq!(battle.validate_action(&a1, &step), Ok(()));
+        assert_eq!(
+            battle.validate_action(&a1, &Action::Move(a1, GridLocation { x: 2, y: 0 })),
+            Err(ActionRejection::NotAdjacent {
+                location: GridLocation { x: 2, y: 0 }
+            })
+        );
+        assert_eq!(
+            battle.validate_action(&a1, &Action::Move(b1, GridLocation { x: 3, y: 1 })),
+            Err(ActionRejection::NotSelf { character_id: b1 })
+        );
+        assert_eq!(battle.validate_action(&a1, &Action::Pass), Ok(()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_legal_actions() -> Result<(), String> {
+        let mut battle = Battle::deserialize_with_actors(
+            AI_ONLY_BATTLE,
+            None,
+            Box::new(SeededRandomProvider::new(1)),
+            |id, _| Box::new(DumbActor { character_id: id }),
+        )?;
+        let a1 = CharacterId::new(0);
+        let a2 = CharacterId::new(1);
+        let b1 = CharacterId::new(2);
+        assert_eq!(battle.legal_actions(a1), vec![Action::Pass]);
+
+        let character = battle.characters.get_mut(&a1).unwrap();
+        let dart = *character
+            .deck
+            .iter()
+            .find(|card| card.card_id == CardId::new(1))
+            .unwrap();
+        character.hand.push(dart);
+        character.remaining_actions = 1;
+        character.movement = 1;
+
+        // B1 is 5 away so out of the dart's range of 3, and A1 cannot target itself
+        let legal_actions = battle.legal_actions(a1);
+        assert_eq!(
+            legal_actions,
+            vec![
+                Action::Act(dart, a2),
+                Action::Move(a1, GridLocation { x: 1, y: 0 }),
+                Action::Move(a1, GridLocation { x: 0, y: 1 }),
+                Action::Pass,
+            ]
+        );
+        assert!(!legal_actions.contains(&Action::Act(dart, b1)));
+        for action in &legal_actions {
+            assert_eq!(battle.validate_action(&a1, action), Ok(()));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_undo() -> Result<(), String> {
+        let a1 = CharacterId::new(0);
+        let mut battle = Battle::deserialize_with_actors(
+            AI_ONLY_BATTLE,
+            None,
+            Box::new(SeededRandomProvider::new(5)),
+            |character_id, _| -> Box<dyn Actor> {
+                if character_id == a1 {
+                    Box::new(ReplayActor::new(
+                        character_id,
+                        vec![
+                            Action::Move(a1, GridLocation { x: 1, y: 0 }),
+                            Action::Undo,
+                            Action::Undo,
+                            Action::Move(a1, GridLocation { x: 0, y: 1 }),
+                            Action::Pass,
+                        ],
+                    ))
+                } else {
+                    Box::new(DumbActor { character_id })
+                }
+            },
+        )?;
+        battle.players.insert(a1);
+        battle.advance().await.unwrap();
+
+        let accepted: Vec<_> = battle
+            .action_log
+            .iter()
+            .filter(|entry| entry.character_id == a1)
+            .map(|entry| (&entry.action, entry.accepted))
+            .collect();
+        assert_eq!(
+            accepted[1..3],
+            [(&Action::Undo, true), (&Action::Undo, false)]
+        );
+        assert_eq!(battle.board.find(&BoardItem::Character(a1)), Some((0, 1)));
+        // The undone move stays in the history, followed by the undo
+        let moves: Vec<_> = battle
+            .events
+            .iter()
+            .filter(|event| match event {
+                BattleEvent::Moved { character_id, .. }
+                | BattleEvent::ActionUndone { character_id } => *character_id == a1,
+                _ => false,
+            })
+            .collect();
+        assert_eq!(
+            moves,
+            [
+                &BattleEvent::Moved {
+                    character_id: a1,
+                    from: GridLocation { x: 0, y: 0 },
+                    to: GridLocation { x: 1, y: 0 },
+                },
+                &BattleEvent::ActionUndone { character_id: a1 },
+                &BattleEvent::Moved {
+                    character_id: a1,
+                    from: GridLocation { x: 0, y: 0 },
+                    to: GridLocation { x: 0, y: 1 },
+                },
+            ]
+        );
+        assert!(history_json(&battle).contains("took back their last action"));
+
+        // Between turns, and once anything random was revealed, there is nothing to undo
+        assert_eq!(
+            battle.validate_action(&a1, &Action::Undo),
+            Err(ActionRejection::NothingToUndo)
+        );
+        battle.undo_history.push(UndoSnapshot::new(&battle), true);
+        assert_eq!(
+            battle.validate_action(&a1, &Action::Undo),
+            Err(ActionRejection::RandomRevealed)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_triggers_and_durations() -> Result<(), String> {
+        let data = ai_only_battle_with(|data| {
+            let effects = data["effects"].as_array_mut().unwrap();
+            effects.push(serde_json::json!({
+                "id": 1,
+                "name": "Thorns",
+                "description": "Hurts attackers",
+                "image": "thorns.png",
+                "triggers": ["on_damaged"],
+                "actions": [{ "type": "damage", "target": "others", "amount": 1 }]
+            }));
+            effects.push(serde_json::json!({
+                "id": 2,
+                "name": "Blessing",
+                "description": "Heals at the end of the round",
+                "image": "blessing.png",
+                "triggers": ["round_end"],
+                "actions": [{ "type": "heal", "target": "self", "amount": 1 }],
+                "duration": 2
+            }));
+        });
+        let mut battle = Battle::deserialize_with_actors(
+            &data,
+            None,
+            Box::new(SeededRandomProvider::new(1)),
+            |character_id, _| Box::new(DumbActor { character_id }),
+        )?;
+        let (a1, b1) = (CharacterId::new(0), CharacterId::new(2));
+        let (thorns, blessing) = (EffectId::new(1), EffectId::new(2));
+
+        // Thorns on both sides only strike back once
+        battle.apply_effect(a1, thorns, None);
+        battle.apply_effect(b1, thorns, None);
+        battle.try_run_card_action(
+            a1,
+            b1,
+            None,
+            &CardAction::Damage {
+                target: Target::Others,
+                amount: U64Range(3, 3),
+                area: U64Range(0, 0),
+                damage_type: DamageType::Physical,
+            },
+        );
+        assert_eq!(battle.characters[&b1].health, Health::new(12));
+        assert_eq!(battle.characters[&a1].health, Health::new(7));
+
+        battle.apply_effect(a1, blessing, None);
+        battle.trigger_round_effects(Trigger::RoundEnd);
+        assert_eq!(battle.characters[&a1].health, Health::new(8));
+
+        battle.expire_effects(a1);
+        assert_eq!(battle.characters[&a1].effect_stacks(blessing), 1);
+        battle.expire_effects(a1);
+        assert_eq!(battle.characters[&a1].effect_stacks(blessing), 0);
+        assert!(battle.events.contains(&BattleEvent::EffectExpired {
+            character_id: a1,
+            effect_id: blessing,
+        }));
+        assert!(history_json(&battle).contains("wore off"));
+
+        // Reapplying an effect restarts its duration
+        battle.apply_effect(b1, blessing, None);
+        battle.expire_effects(b1);
+        battle.apply_effect(b1, blessing, None);
+        battle.expire_effects(b1);
+        assert_eq!(battle.characters[&b1].effect_stacks(blessing), 2);
+        assert_eq!(battle.remove_effect(b1, blessing, 1), 1);
+        assert_eq!(battle.characters[&b1].effect_stacks(blessing), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stacking_effects() -> Result<(), String> {
+        let data = ai_only_battle_with(|data| data["effects"][0]["max_stacks"] = 2.into());
+        let mut battle = Battle::deserialize_with_actors(
+            &data,
+            None,
+            Box::new(SeededRandomProvider::new(1)),
+            |character_id, _| Box::new(DumbActor { character_id }),
+        )?;
+        let (a1, b1) = (CharacterId::new(0), CharacterId::new(2));
+        let poison = EffectId::new(0);
+
+        for _ in 0..3 {
+            battle.apply_effect(b1, poison, Some(a1));
+        }
+        assert_eq!(
+            battle.characters[&b1].effects,
+            vec![EffectInstance {
+                effect_id: poison,
+                stacks: 2,
+                remaining_turns: None,
+                source: Some(a1),
+            }]
+        );
+
+        // Poison deals 1 damage per stack, and each stack rolls to wear off on its own
+        battle.random_provider = Box::new(ReplayRandomProvider::new(vec![
+            RandomPick::U64 {
+                lower_bound: 0,
+                upper_bound: 0,
+                value: 0,
+            },
+            RandomPick::U64 {
+                lower_bound: 2,
+                upper_bound: 2,
+                value: 2,
+            },
+            RandomPick::U32 {
+                lower_bound: 0,
+                upper_bound: u32::MAX,
+                value: u32::MAX,
+            },
+            RandomPick::U32 {
+                lower_bound: 0,
+                upper_bound: u32::MAX,
+                value: 0,
+            },
+        ]));
+        battle.trigger_effects(b1, b1, Trigger::TurnStart);
+        assert_eq!(battle.characters[&b1].health, Health::new(13));
+        assert_eq!(battle.characters[&b1].effect_stacks(poison), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_defenses() -> Result<(), String> {
+        let mut battle = Battle::deserialize_with_actors(
+            AI_ONLY_BATTLE,
+            None,
+            Box::new(SeededRandomProvider::new(1)),
+            |character_id, _| Box::new(DumbActor { character_id }),
+        )?;
+        let (a1, b1) = (CharacterId::new(0), CharacterId::new(2));
+        let defend = |defense, amount| CardAction::GainDefense {
+            target: Target::Me,
+            defense,
+            amount: U64Range(amount, amount),
+        };
+        battle.try_run_card_action(b1, b1, None, &defend(Defense::Armor, 1));
+        battle.try_run_card_action(b1, b1, None, &defend(Defense::Block, 2));
+        battle.try_run_card_action(
+            a1,
+            b1,
+            None,
+            &CardAction::Damage {
+                target: Target::Others,
+                amount: U64Range(5, 5),
+                area: U64Range(0, 0),
+                damage_type: DamageType::Physical,
+            },
+        );
+        assert_eq!(battle.characters[&b1].health, Health::new(13));
+        assert!(battle.events.contains(&BattleEvent::DamageDealt {
+            source_id: a1,
+            target_id: b1,
+            amount: 2,
+            blocked: 3,
+            damage_type: DamageType::Physical,
+            card_id: None,
+        }));
+        assert!(history_json(&battle).contains("(3 blocked)"));
+
+        battle.try_run_card_action(
+            a1,
+            b1,
+            None,
+            &CardAction::StripDefense {
+                target: Target::Others,
+                defense: Defense::Armor,
+                amount: None,
+            },
+        );
+        assert_eq!(battle.characters[&b1].armor, 0);
+        assert_eq!(
+            battle.events.last(),
+            Some(&BattleEvent::DefenseLost {
+                character_id: b1,
+                defense: Defense::Armor,
+                amount: 1,
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_energy() -> Result<(), String> {
+        let data = ai_only_battle_with(|data| {
+            data["default_max_energy"] = 3.into();
+            data["default_energy_per_turn"] = 1.into();
+            data["cards"][0]["cost"] = 2.into();
+        });
+        let mut battle = Battle::deserialize_with_actors(
+            &data,
+            None,
+            Box::new(SeededRandomProvider::new(1)),
+            |character_id, _| Box::new(DumbActor { character_id }),
+        )?;
+        let (a1, b1) = (CharacterId::new(0), CharacterId::new(2));
+        battle.board.grid.clear(3, 2);
+        battle.board.grid.set(1, 0, BoardItem::Character(b1));
+        let slash = battle.characters[&a1].deck[0];
+        let character = battle.characters.get_mut(&a1).unwrap();
+        character.hand.push(slash);
+        character.remaining_actions = 2;
+
+        assert_eq!(
+            battle.validate_action(&a1, &Action::Act(slash, b1)),
+            Err(ActionRejection::NotEnoughEnergy { energy: 0, cost: 2 })
+        );
+
+        // Energy is capped at the maximum
+        battle.try_run_card_action(
+            a1,
+            a1,
+            None,
+            &CardAction::GainEnergy {
+                target: Target::Me,
+                amount: U64Range(5, 5),
+            },
+        );
+        assert_eq!(battle.characters[&a1].energy, 3);
+        assert_eq!(
+            battle.events.last(),
+            Some(&BattleEvent::EnergyGained {
+                character_id: a1,
+                amount: 3,
+            })
+        );
+
+        assert_eq!(battle.handle_action(&a1, Action::Act(slash, b1)), Ok(()));
+        assert_eq!(battle.characters[&a1].energy, 1);
+        assert_eq!(battle.characters[&a1].remaining_actions, 1);
+
+        battle.try_run_card_action(
+            b1,
+            a1,
+            None,
+            &CardAction::LoseEnergy {
+                target: Target::Others,
+                amount: U64Range(2, 2),
+            },
+        );
+        assert_eq!(battle.characters[&a1].energy, 0);
+        assert!(history_json(&battle).contains("lost 1 energy"));
+        Ok(())
+    }
+
+    /// The AI battle with a card for A1 that draws, discards and reorders its deck
+    fn sift_battle_data(hand_size: usize) -> String {
+        ai_only_battle_with(|data| add_sift(data, hand_size))
+    }
+
+    /// Adds the card of `sift_battle_data` to battle file JSON
+    fn add_sift(data: &mut serde_json::Value, hand_size: usize) {
+        data["default_hand_size"] = hand_size.into();
+        data["cards"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({
+                "id": 2,
+                "name": "Sift",
+                "description": "Draw 2, discard 1 and reorder the top 2 cards of your deck",
+                "actions": [
+                    { "type": "draw", "target": "self", "amount": 2 },
+                    { "type": "discard", "target": "self", "amount": 1 },
+                    { "type": "reorder_deck", "target": "self", "amount": 2 }
+                ]
+            }));
+        data["teams"][0]["members"][0]["cards"] = serde_json::json!([2, 0, 0, 1, 1, 0]);
+    }
+
+    #[test]
+    fn test_deck_manipulation() -> Result<(), String> {
+        let mut battle = Battle::deserialize_with_actors(
+            &sift_battle_data(2),
+            None,
+            Box::new(SeededRandomProvider::new(1)),
+            |character_id, _| Box::new(DumbActor { character_id }),
+        )?;
+        let a1 = CharacterId::new(0);
+        let character = battle.characters.get_mut(&a1).unwrap();
+        let sift_index = character
+            .deck
+            .iter()
+            .position(|instance| instance.card_id == CardId::new(2))
+            .unwrap();
+        let sift = character.deck.remove(sift_index);
+        character.hand.push(sift);
+        character.remaining_actions = 1;
+        let deck = character.deck.clone();
+
+        // The card waits for its discard after drawing, and nothing else may happen meanwhile
+        assert_eq!(battle.handle_action(&a1, Action::Act(sift, a1)), Ok(()));
+        let choice = battle.pending_choice.clone().unwrap();
+        assert_eq!(choice.kind, CardChoiceKind::Discard);
+        assert_eq!(choice.count, 1);
+        assert_eq!(choice.cards, deck[..2]);
+        assert_eq!(battle.legal_actions(a1), [Action::Choose(vec![deck[0]])]);
+        assert_eq!(
+            battle.validate_action(&a1, &Action::Pass),
+            Err(ActionRejection::ChoicePending)
+        );
+        assert_eq!(
+            battle.handle_action(&a1, Action::Choose(vec![deck[4]])),
+            Err(ActionRejection::InvalidChoice { count: 1 })
+        );
+        assert_eq!(
+            battle.handle_action(&a1, Action::Choose(vec![deck[1]])),
+            Ok(())
+        );
+
+        let choice = battle.pending_choice.clone().unwrap();
+        assert_eq!(choice.kind, CardChoiceKind::Reorder);
+        assert_eq!(choice.cards, deck[2..4]);
+        assert_eq!(
+            battle.handle_action(&a1, Action::Choose(vec![deck[3], deck[2]])),
+            Ok(())
+        );
+        assert_eq!(battle.pending_choice, None);
+        let character = &battle.characters[&a1];
+        assert_eq!(character.hand, [deck[0]]);
+        assert_eq!(character.discard, [deck[1], sift]);
+        assert_eq!(character.deck, [deck[3], deck[2], deck[4]]);
+        assert!(history_json(&battle).contains("drew 2 cards"));
+
+        // Outside of a played card the default is picked straight away
+        battle.try_run_card_action(
+            a1,
+            a1,
+            None,
+            &CardAction::Exhaust {
+                target: Target::Me,
+                amount: U64Range(1, 1),
+                random: false,
+            },
+        );
+        assert!(battle.characters[&a1].hand.is_empty());
+        assert_eq!(battle.characters[&a1].discard.len(), 2);
+
+        // Added cards get a new instance id
+        battle.try_run_card_action(
+            a1,
+            a1,
+            None,
+            &CardAction::AddCard {
+                target: Target::Me,
+                card: CardId::new(1),
+                pile: Pile::Hand,
+            },
+        );
+        let Some(&BattleEvent::CardAdded {
+            character_id,
+            card: added,
+            pile: Pile::Hand,
+        }) = battle.events.last()
+        else {
+            panic!("Adding a card should be recorded");
+        };
+        assert_eq!((character_id, added.card_id), (a1, CardId::new(1)));
+        assert_eq!(battle.characters[&a1].hand, [added]);
+        assert!(
+            battle
+                .characters
+                .values()
+                .flat_map(|character| character.deck.iter().chain(&character.discard))
+                .all(|instance| instance.card_instance_id != added.card_instance_id)
+        );
+
+        battle.try_run_card_action(
+            a1,
+            a1,
+            None,
+            &CardAction::Discard {
+                target: Target::Me,
+                amount: U64Range(1, 1),
+                random: true,
+            },
+        );
+        assert_eq!(battle.characters[&a1].discard.last(), Some(&added));
+
+        battle.try_run_card_action(
+            a1,
+            a1,
+            None,
+            &CardAction::ShuffleDiscard { target: Target::Me },
+        );
+        let character = &battle.characters[&a1];
+        assert!(character.discard.is_empty());
+        assert_eq!(character.deck.len(), 6);
+        assert!(character.deck.contains(&added) && character.deck.contains(&sift));
+        assert_eq!(
+            battle.events.last(),
+            Some(&BattleEvent::DiscardShuffled { character_id: a1 })
+        );
+
+        let unknown_card = ai_only_battle_with(|data| {
+            add_sift(data, 2);
+            data["cards"][2]["actions"][0] = serde_json::json!({
+                "type": "add_card", "target": "self", "card": 9, "pile": "hand"
+            });
+        });
+        assert!(
+            Battle::deserialize_with_actors(
+                &unknown_card,
+                None,
+                Box::new(SeededRandomProvider::new(1)),
+                |character_id, _| Box::new(DumbActor { character_id }),
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_card_choice_during_turn() -> Result<(), String> {
+        let a1 = CharacterId::new(0);
+        // Everyone starts with their whole deck in hand
+        let data = sift_battle_data(6);
+        let probe = Battle::deserialize_with_actors(
+            &data,
+            None,
+            Box::new(SeededRandomProvider::new(1)),
+            |character_id, _| Box::new(DumbActor { character_id }),
+        )?;
+        let sift = *probe.characters[&a1]
+            .deck
+            .iter()
+            .find(|instance| instance.card_id == CardId::new(2))
+            .unwrap();
+        let mut battle = Battle::deserialize_with_actors(
+            &data,
+            None,
+            Box::new(SeededRandomProvider::new(1)),
+            |character_id, _| -> Box<dyn Actor> {
+                if character_id == a1 {
+                    Box::new(ReplayActor::new(
+                        character_id,
+                        vec![Action::Act(sift, a1), Action::Pass],
+                    ))
+                } else {
+                    Box::new(DumbActor { character_id })
+                }
+            },
+        )?;
+        battle.advance().await.unwrap();
+
+        // The replay has no choice recorded so the default pick is logged in its place
+        let discarded = battle.characters[&a1].discard[0];
+        let actions: Vec<_> = battle
+            .action_log
+            .iter()
+            .filter(|entry| entry.character_id == a1)
+            .map(|entry| (&entry.action, entry.accepted))
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                (&Action::Act(sift, a1), true),
+                (&Action::Choose(vec![discarded]), true),
+                (&Action::Pass, true),
+            ]
+        );
+        assert_eq!(battle.characters[&a1].discard, [discarded, sift]);
+        assert_eq!(battle.pending_choice, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resistances() -> Result<(), String> {
+        let resistant = |fire: f64| {
+            ai_only_battle_with(|data| {
+                data["resistances"] =
+                    serde_json::json!({ "Machine": { "poison": 0, "electric": 2 } });
+                members(data)[2]["resistances"] = serde_json::json!({ "fire": fire });
+            })
+        };
+        let data = resistant(0.5);
+        let mut battle = Battle::deserialize_with_actors(
+            &data,
+            None,
+            Box::new(SeededRandomProvider::new(1)),
+            |character_id, _| Box::new(DumbActor { character_id }),
+        )?;
+        let (a1, a2, b1) = (
+            CharacterId::new(0),
+            CharacterId::new(1),
+            CharacterId::new(2),
+        );
+        let damage = |damage_type, amount| CardAction::Damage {
+            target: Target::Others,
+            amount: U64Range(amount, amount),
+            area: U64Range(0, 0),
+            damage_type,
+        };
+
+        battle.try_run_card_action(a1, a2, None, &damage(DamageType::Poison, 4));
+        assert_eq!(battle.characters[&a2].health, Health::new(6));
+        battle.try_run_card_action(a1, a2, None, &damage(DamageType::Electric, 2));
+        assert_eq!(battle.characters[&a2].health, Health::new(2));
+        battle.try_run_card_action(a1, b1, None, &damage(DamageType::Fire, 4));
+        assert_eq!(battle.characters[&b1].health, Health::new(13));
+        battle.try_run_card_action(a2, a1, None, &damage(DamageType::Poison, 3));
+        assert_eq!(battle.characters[&a1].health, Health::new(5));
+
+        assert!(battle.events.contains(&BattleEvent::DamageDealt {
+            source_id: a1,
+            target_id: b1,
+            amount: 2,
+            blocked: 0,
+            damage_type: DamageType::Fire,
+            card_id: None,
+        }));
+        assert!(history_json(&battle).contains("fire damage"));
+
+        assert!(
+            Battle::deserialize_with_actors(
+                &resistant(-1.0),
+                None,
+                Box::new(SeededRandomProvider::new(1)),
+                |character_id, _| Box::new(DumbActor { character_id }),
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    /// Deserializes `AI_ONLY_BATTLE` with the turn order and a stat each for A1, A2 and B1
+    fn new_ordered_battle(
+        turn_order: &str,
+        stats: [Option<(&str, u64)>; 3],
+    ) -> Result<Battle, String> {
+        let data = ai_only_battle_with(|data| {
+            data["turn_order"] = turn_order.into();
+            for (member, stat) in members(data).into_iter().zip(stats) {
+                if let Some((name, value)) = stat {
+                    member[name] = value.into();
+                }
+            }
+        });
+        Battle::deserialize_with_actors(
+            &data,
+            None,
+            Box::new(SeededRandomProvider::new(1)),
+            |character_id, _| Box::new(DumbActor { character_id }),
+        )
+    }
+
+    fn turn_order(battle: &mut Battle) -> Vec<usize> {
+        battle
+            .build_turns()
+            .iter()
+            .map(|turn| turn.character.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_turn_order() -> Result<(), String> {
+        let mut battle = new_ordered_battle("team_by_team", [None, None, None])?;
+        assert_eq!(turn_order(&mut battle), [0, 1, 2]);
+
+        let mut battle = new_ordered_battle("interleaved", [None, None, None])?;
+        assert_eq!(turn_order(&mut battle), [0, 2, 1]);
+
+        let mut battle = new_ordered_battle(
+            "initiative",
+            [None, Some(("initiative", 3)), Some(("initiative", 5))],
+        )?;
+        assert_eq!(turn_order(&mut battle), [2, 1, 0]);
+
+        // A1 is twice as fast as B1, and A2 half as fast
+        let mut battle =
+            new_ordered_battle("speed", [Some(("speed", 20)), Some(("speed", 5)), None])?;
+        assert_eq!(turn_order(&mut battle), [0, 0, 2]);
+        assert_eq!(turn_order(&mut battle), [0, 0, 1, 2]);
+
+        battle.characters.require_mut(&CharacterId::new(2)).health = Health::new(0);
+        assert_eq!(turn_order(&mut battle), [0, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_initiative_roll() -> Result<(), String> {
+        let data = ai_only_battle_with(|data| {
+            data["turn_order"] = "initiative".into();
+            data["initiative_roll"] = serde_json::json!([1, 20]);
+        });
+        let random_provider = RecordingRandomProvider::new(Box::new(SeededRandomProvider::new(3)));
+        let picks = random_provider.log();
+        let mut battle = Battle::deserialize_with_actors(
+            &data,
+            None,
+            Box::new(random_provider),
+            |character_id, _| Box::new(DumbActor { character_id }),
+        )?;
+
+        let order = turn_order(&mut battle);
+        let rolls: Vec<u64> = picks
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|pick| match pick {
+                RandomPick::U64 { value, .. } => *value,
+                pick => panic!("Unexpected pick {pick:?}"),
+            })
+            .collect();
+        assert_eq!(rolls.len(), 3);
+        // Highest roll first
+        let mut expected: Vec<usize> = (0..3).collect();
+        expected.sort_by(|a, b| rolls[*b].cmp(&rolls[*a]));
+        assert_eq!(order, expected);
+
+        assert!(
+            Battle::deserialize_with_actors(
+                &ai_only_battle_with(|data| members(data)[0]["speed"] = 0.into()),
+                None,
+                Box::new(SeededRandomProvider::new(3)),
+                |character_id, _| Box::new(DumbActor { character_id }),
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    /// Never decides on an action, like a player who walked away
+    struct IdleActor {
+        character_id: CharacterId,
+    }
+
+    #[async_trait]
+    impl Actor for IdleActor {
+        fn get_character_id(&self) -> &CharacterId {
+            &self.character_id
+        }
+
+        async fn act(&self, _battle: &Battle) -> ActionResult {
+            futures::future::pending().await
+        }
+
+        async fn choose_cards(&self, _battle: &Battle, _choice: &CardChoice) -> Vec<CardInstance> {
+            futures::future::pending().await
+        }
+
+        async fn on_game_over(&self, _battle: &Battle) {}
+    }
+
+    fn new_timed_battle(
+        on_turn_timeout: &str,
+        change: impl FnOnce(&mut serde_json::Value),
+    ) -> Result<Battle, String> {
+        let data = ai_only_battle_with(|data| {
+            data["turn_time_limit"] = 0.into();
+            data["on_turn_timeout"] = on_turn_timeout.into();
+            change(data);
+        });
+        Battle::deserialize_with_actors(
+            &data,
+            None,
+            Box::new(SeededRandomProvider::new(5)),
+            |character_id, _| -> Box<dyn Actor> {
+                if character_id == CharacterId::new(0) {
+                    Box::new(IdleActor { character_id })
+                } else {
+                    Box::new(DumbActor { character_id })
+                }
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_turn_timeout_passes() -> Result<(), String> {
+        let mut battle = new_timed_battle("pass", |_| {})?;
+        let a1 = CharacterId::new(0);
+        battle.advance().await.unwrap();
+
+        let timeouts: Vec<_> = battle
+            .events
+            .iter()
+            .filter(|event| matches!(event, BattleEvent::TurnTimedOut { .. }))
+            .collect();
+        assert_eq!(timeouts, [&BattleEvent::TurnTimedOut { character_id: a1 }]);
+        let actions: Vec<_> = battle
+            .action_log
+            .iter()
+            .filter(|entry| entry.character_id == a1)
+            .map(|entry| &entry.action)
+            .collect();
+        assert_eq!(actions, [&Action::Pass]);
+        assert!(battle.turn_deadline.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_turn_timeout_hands_over_to_ai() -> Result<(), String> {
+        let mut battle = new_timed_battle("ai", |_| {})?;
+        battle.run_to_completion().await.unwrap();
+
+        let a1_turns = battle
+            .events
+            .iter()
+            .filter(|event| {
+                matches!(event, BattleEvent::TurnTimedOut { character_id } if *character_id == CharacterId::new(0))
+            })
+            .count();
+        assert!(a1_turns > 0);
+        assert!(battle.end_state.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_turn_timeout_ai_makes_choices() -> Result<(), String> {
+        let mut battle = new_timed_battle("ai", |data| {
+            add_sift(data, 2);
+            data["teams"][0]["members"][0]["cards"] = serde_json::json!([2, 2, 2, 2, 2]);
+        })?;
+        let a1 = CharacterId::new(0);
+        tokio::time::timeout(Duration::from_secs(5), battle.advance())
+            .await
+            .expect("The AI playing A1's turn should make its choices")
+            .unwrap();
+
+        assert!(
+            battle
+                .action_log
+                .iter()
+                .any(|entry| entry.character_id == a1
+                    && matches!(entry.action, Action::Choose(_))
+                    && entry.accepted)
+        );
+        assert!(!battle.characters[&a1].discard.is_empty());
+        Ok(())
+    }
 }
@@ -1,13 +1,17 @@
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
 };
 
 use crate::{
     Actor, Battle, Board, BoardItem, Card, CardId, CardInstance, CardInstanceId, Character,
-    CharacterId, CharacterRace, DumbActor, Effect, EffectId, EndCondition, EndConditionCriterion,
-    Health, NumericExt, Object, ObjectId, ObjectInstance, ObjectInstanceId, RandomProvider, Team,
-    TeamId, TerminalActor, U64Range, battle_file, web_actor::WebActor,
+    CharacterId, CharacterRace, CountingRandomProvider, Effect, EffectId, EndCondition,
+    EndConditionCriterion, Health, NumericExt, Object, ObjectId, ObjectInstance, ObjectInstanceId,
+    READINESS_PER_TURN, RandomProvider, Team, TeamId, TerminalActor, TurnOrder, TurnTimeout,
+    TurnTimer, U64Range, UndoHistory, battle_file, create_ai_actor,
+    web_actor::{ServerConfig, WebActor, WebServer},
 };
 use futures::future::join_all;
 
@@ -149,48 +153,145 @@ fn get_all_team_character_ids(
 }
 
 impl Battle {
+    /// `server_options` override the battle file's options for the web server hosting players
     pub async fn deserialize(
         data: &str,
         asset_directory: Option<PathBuf>,
         random_provider: Box<dyn RandomProvider>,
+        server_options: battle_file::ServerOptions,
     ) -> Result<Self, String> {
-        let battle = battle_file::Battle::parse_from_str(data)?;
-
-        validate_ids(&battle.cards, |entry| entry.id)?;
-        validate_ids(&battle.effects, |entry| entry.id)?;
-        validate_ids(&battle.objects, |entry| entry.id)?;
-
-        let mut current_card_instance_id = 0usize;
-        let mut current_object_instance_id = 0usize;
-
-        let canonical_asset_directory =
-            asset_directory.map(|path_buf| path_buf.canonicalize().unwrap());
-
-        Ok(Battle {
-            history: vec![],
-            introduction: battle.introduction.clone(),
-            random_provider,
-            default_turn_actions: 1,
-            background_image: deserialize_background_image(&battle),
-            board: deserialize_board(&battle, &mut current_card_instance_id)?,
-            characters: deserialize_characters(
-                &battle,
-                &mut current_card_instance_id,
-                &mut current_object_instance_id,
-            ),
-            cards: deserialize_cards(&battle),
-            effects: deserialize_effects(&battle),
-            objects: deserialize_objects(&battle),
-            teams: deserialize_teams(&battle),
-            actors: deserialize_actors(&battle, canonical_asset_directory.as_deref()).await,
-            round: 0,
-            asset_directory: canonical_asset_directory,
-            end_conditions: deserialize_end_conditions(&battle)?,
-            end_state: None,
-        })
+        let battle_file = battle_file::Battle::parse_from_str(data)?;
+        let mut battle = deserialize_battle(&battle_file, asset_directory, random_provider)?;
+        let server_config = ServerConfig::from(server_options.or(battle_file.server.clone()));
+        let web_server = start_web_server(
+            &battle_file,
+            &server_config,
+            battle.asset_directory.as_deref(),
+        )?;
+        battle.actors = deserialize_actors(&battle_file, web_server.clone()).await;
+        if let Some(web_server) = web_server {
+            // Keeps spectators up to date with every turn, not just those of players
+            battle.add_observer(Box::new(web_server));
+        }
+        Ok(battle)
+    }
+
+    /// Like `deserialize` but every team member is controlled by the actor returned from
+    /// `create_actor` instead of the default player and AI actors.
+    pub fn deserialize_with_actors(
+        data: &str,
+        asset_directory: Option<PathBuf>,
+        random_provider: Box<dyn RandomProvider>,
+        create_actor: impl Fn(CharacterId, &battle_file::TeamMember) -> Box<dyn Actor>,
+    ) -> Result<Self, String> {
+        let battle_file = battle_file::Battle::parse_from_str(data)?;
+        let mut battle = deserialize_battle(&battle_file, asset_directory, random_provider)?;
+        battle.actors = get_all_team_character_ids(&battle_file)
+            .into_iter()
+            .map(|(team_id, character_id, team_member)| {
+                (
+                    TeamId::new(team_id.try_into().unwrap()),
+                    create_actor(CharacterId::new(character_id), team_member),
+                )
+            })
+            .collect();
+        Ok(battle)
+    }
+}
+
+fn deserialize_turn_order(battle: &battle_file::Battle) -> TurnOrder {
+    match battle.turn_order {
+        battle_file::TurnOrder::TeamByTeam => TurnOrder::TeamByTeam,
+        battle_file::TurnOrder::Interleaved => TurnOrder::Interleaved,
+        battle_file::TurnOrder::Initiative => TurnOrder::Initiative {
+            roll: battle
+                .initiative_roll
+                .as_ref()
+                .map(normalize_maybe_u64_range),
+        },
+        battle_file::TurnOrder::Speed => TurnOrder::Speed,
     }
 }
 
+/// Builds the battle without any actors attached
+fn deserialize_battle(
+    battle: &battle_file::Battle,
+    asset_directory: Option<PathBuf>,
+    random_provider: Box<dyn RandomProvider>,
+) -> Result<Battle, String> {
+    validate_ids(&battle.cards, |entry| entry.id)?;
+    validate_ids(&battle.effects, |entry| entry.id)?;
+    validate_ids(&battle.objects, |entry| entry.id)?;
+
+    let mut current_card_instance_id = 0usize;
+    let mut current_object_instance_id = 0usize;
+
+    let canonical_asset_directory =
+        asset_directory.map(|path_buf| path_buf.canonicalize().unwrap());
+
+    let random_provider = CountingRandomProvider::new(random_provider);
+    let effects = deserialize_effects(battle);
+
+    Ok(Battle {
+        history: vec![],
+        events: vec![],
+        action_log: vec![],
+        introduction: battle.introduction.clone(),
+        random_picks: random_provider.count(),
+        random_provider: Box::new(random_provider),
+        default_turn_actions: 1,
+        background_image: deserialize_background_image(battle),
+        board: deserialize_board(battle, &mut current_card_instance_id)?,
+        characters: deserialize_characters(
+            battle,
+            &mut current_card_instance_id,
+            &mut current_object_instance_id,
+            &effects,
+        )?,
+        cards: Arc::new(deserialize_cards(battle)),
+        effects: Arc::new(effects),
+        objects: Arc::new(deserialize_objects(battle)),
+        teams: Arc::new(deserialize_teams(battle)),
+        actors: vec![],
+        observers: vec![],
+        round: 0,
+        asset_directory: canonical_asset_directory,
+        end_conditions: Arc::new(deserialize_end_conditions(battle)?),
+        end_state: None,
+        turn_timers: deserialize_turn_timers(battle),
+        turn_deadline: None,
+        players: get_all_team_character_ids(battle)
+            .into_iter()
+            .filter(|(_team_id, _character_id, team_member)| team_member.is_player)
+            .map(|(_team_id, character_id, _team_member)| CharacterId::new(character_id))
+            .collect(),
+        undo_history: UndoHistory::default(),
+        turn_order: deserialize_turn_order(battle),
+        reacting: false,
+        pending_choice: None,
+        card_in_play: None,
+    })
+}
+
+fn deserialize_turn_timers(battle: &battle_file::Battle) -> HashMap<CharacterId, TurnTimer> {
+    get_all_team_character_ids(battle)
+        .into_iter()
+        .filter_map(|(_team_id, character_id, team_member)| {
+            let limit = team_member.turn_time_limit.or(battle.turn_time_limit)?;
+            Some((
+                CharacterId::new(character_id),
+                TurnTimer {
+                    limit: Duration::from_secs(limit),
+                    on_timeout: match battle.on_turn_timeout {
+                        battle_file::TurnTimeout::Pass => TurnTimeout::Pass,
+                        battle_file::TurnTimeout::Ai => TurnTimeout::Ai(team_member.ai),
+                    },
+                },
+            ))
+        })
+        .collect()
+}
+
 fn deserialize_background_image(battle: &battle_file::Battle) -> Option<String> {
     battle
         .board
@@ -205,6 +306,7 @@ fn deserialize_card_action(card_action: &battle_file::CardAction) -> crate::Card
             target,
             amount,
             area,
+            damage_type,
         } => crate::CardAction::Damage {
             target: deserialize_target(target),
             amount: normalize_maybe_u64_range(amount),
@@ -212,6 +314,7 @@ fn deserialize_card_action(card_action: &battle_file::CardAction) -> crate::Card
                 .as_ref()
                 .map(normalize_maybe_u64_range)
                 .unwrap_or(U64Range(0, 0)),
+            damage_type: deserialize_damage_type(damage_type),
         },
         battle_file::CardAction::Heal {
             target,
@@ -265,6 +368,91 @@ fn deserialize_card_action(card_action: &battle_file::CardAction) -> crate::Card
         battle_file::CardAction::DestroySelf { chance } => crate::CardAction::DestroySelf {
             chance: deserialize_chance(chance),
         },
+        battle_file::CardAction::GainDefense {
+            target,
+            defense,
+            amount,
+        } => crate::CardAction::GainDefense {
+            target: deserialize_target(target),
+            defense: deserialize_defense(defense),
+            amount: normalize_maybe_u64_range(amount),
+        },
+        battle_file::CardAction::StripDefense {
+            target,
+            defense,
+            amount,
+        } => crate::CardAction::StripDefense {
+            target: deserialize_target(target),
+            defense: deserialize_defense(defense),
+            amount: amount.as_ref().map(normalize_maybe_u64_range),
+        },
+        battle_file::CardAction::GainEnergy { target, amount } => crate::CardAction::GainEnergy {
+            target: deserialize_target(target),
+            amount: normalize_maybe_u64_range(amount),
+        },
+        battle_file::CardAction::LoseEnergy { target, amount } => crate::CardAction::LoseEnergy {
+            target: deserialize_target(target),
+            amount: normalize_maybe_u64_range(amount),
+        },
+        battle_file::CardAction::Draw { target, amount } => crate::CardAction::Draw {
+            target: deserialize_target(target),
+            amount: normalize_maybe_u64_range(amount),
+        },
+        battle_file::CardAction::Discard {
+            target,
+            amount,
+            random,
+        } => crate::CardAction::Discard {
+            target: deserialize_target(target),
+            amount: normalize_maybe_u64_range(amount),
+            random: *random,
+        },
+        battle_file::CardAction::Exhaust {
+            target,
+            amount,
+            random,
+        } => crate::CardAction::Exhaust {
+            target: deserialize_target(target),
+            amount: normalize_maybe_u64_range(amount),
+            random: *random,
+        },
+        battle_file::CardAction::AddCard { target, card, pile } => crate::CardAction::AddCard {
+            target: deserialize_target(target),
+            card: crate::CardId::new(*card),
+            pile: deserialize_pile(pile),
+        },
+        battle_file::CardAction::ShuffleDiscard { target } => crate::CardAction::ShuffleDiscard {
+            target: deserialize_target(target),
+        },
+        battle_file::CardAction::ReorderDeck { target, amount } => crate::CardAction::ReorderDeck {
+            target: deserialize_target(target),
+            amount: normalize_maybe_u64_range(amount),
+        },
+    }
+}
+
+fn deserialize_pile(pile: &battle_file::Pile) -> crate::Pile {
+    match pile {
+        battle_file::Pile::Hand => crate::Pile::Hand,
+        battle_file::Pile::Deck => crate::Pile::Deck,
+        battle_file::Pile::Discard => crate::Pile::Discard,
+    }
+}
+
+fn deserialize_defense(defense: &battle_file::Defense) -> crate::Defense {
+    match defense {
+        battle_file::Defense::Armor => crate::Defense::Armor,
+        battle_file::Defense::Block => crate::Defense::Block,
+        battle_file::Defense::Shield => crate::Defense::Shield,
+    }
+}
+
+fn deserialize_damage_type(damage_type: &battle_file::DamageType) -> crate::DamageType {
+    match damage_type {
+        battle_file::DamageType::Physical => crate::DamageType::Physical,
+        battle_file::DamageType::Fire => crate::DamageType::Fire,
+        battle_file::DamageType::Poison => crate::DamageType::Poison,
+        battle_file::DamageType::Electric => crate::DamageType::Electric,
     }
 }
 
@@ -285,6 +473,13 @@ fn deserialize_effect(effect: &battle_file::Effect) -> crate::Effect {
             .as_ref()
             .map(|triggers| triggers.iter().map(deserailize_trigger).collect())
             .unwrap_or_default(),
+        duration: effect.duration,
+        max_stacks: effect.max_stacks,
+        stacking: match effect.stacking {
+            battle_file::Stacking::Add => crate::Stacking::Add,
+            battle_file::Stacking::Refresh => crate::Stacking::Refresh,
+            battle_file::Stacking::Ignore => crate::Stacking::Ignore,
+        },
     }
 }
 
@@ -304,6 +499,7 @@ fn deserialize_card(card: &battle_file::Card) -> crate::Card {
         description: card.description.clone(),
         flavor: card.flavor.clone(),
         range: card.range.unwrap_or(0),
+        cost: card.cost.unwrap_or(0),
         actions: card.actions.iter().map(deserialize_card_action).collect(),
     }
 }
@@ -320,6 +516,13 @@ fn deserailize_trigger(trigger: &battle_file::Trigger) -> crate::Trigger {
     match trigger {
         battle_file::Trigger::Death => crate::Trigger::Death,
         battle_file::Trigger::TurnStart => crate::Trigger::TurnStart,
+        battle_file::Trigger::TurnEnd => crate::Trigger::TurnEnd,
+        battle_file::Trigger::RoundStart => crate::Trigger::RoundStart,
+        battle_file::Trigger::RoundEnd => crate::Trigger::RoundEnd,
+        battle_file::Trigger::OnDamaged => crate::Trigger::OnDamaged,
+        battle_file::Trigger::OnHeal => crate::Trigger::OnHeal,
+        battle_file::Trigger::OnCardPlayed => crate::Trigger::OnCardPlayed,
+        battle_file::Trigger::OnMove => crate::Trigger::OnMove,
     }
 }
 
@@ -327,66 +530,87 @@ fn deserialize_characters(
     battle: &battle_file::Battle,
     current_card_instance_id: &mut usize,
     current_object_instance_id: &mut usize,
-) -> HashMap<CharacterId, Character> {
+    effects: &HashMap<EffectId, Effect>,
+) -> Result<HashMap<CharacterId, Character>, String> {
     battle
         .teams
         .iter()
         .flat_map(|team| &team.members)
         .enumerate()
         .map(|(index, member)| {
-            (
-                CharacterId::new(index),
-                Character {
-                    id: CharacterId::new(index),
-                    name: member.name.clone(),
-                    effects: member
-                        .effects
-                        .iter()
-                        .map(|effect| EffectId::new(*effect))
-                        .collect(),
-                    race: match member.race {
-                        battle_file::Race::Human => CharacterRace::Human,
-                        battle_file::Race::Machine => CharacterRace::Machine,
-                    },
-                    hand: vec![],
-                    remaining_actions: 0,
-                    image: member.image.clone(),
-                    deck: member
-                        .cards
-                        .iter()
-                        .map(|card_id| CardInstance {
-                            card_id: CardId::new(*card_id),
-                            card_instance_id: CardInstanceId::new(current_card_instance_id.inc()),
-                        })
-                        .collect(),
-                    discard: vec![],
-                    health: Health::new(member.base_health),
-                    max_health: Health::new(member.max_health.unwrap_or(member.base_health)),
-                    hand_size: member.hand_size.unwrap_or(battle.default_hand_size),
-                    movement: 0,
-                    default_movement: member
-                        .movement
-                        .unwrap_or(battle.default_movement.unwrap_or(0)),
-                    contains: member
-                        .contains
-                        .iter()
-                        .map(|content| match content {
-                            battle_file::Content::Card(id) => {
-                                crate::Content::Card(CardInstance::new(
-                                    CardId::new(*id),
-                                    CardInstanceId::new(current_card_instance_id.inc()),
-                                ))
-                            }
-                            battle_file::Content::Object(id) => {
-                                crate::Content::Object(ObjectInstance::new(
-                                    ObjectId::new(*id),
-                                    ObjectInstanceId::new(current_object_instance_id.inc()),
-                                ))
-                            }
-                        })
-                        .collect(),
+            let mut character = Character {
+                id: CharacterId::new(index),
+                name: member.name.clone(),
+                effects: vec![],
+                race: match member.race {
+                    battle_file::Race::Human => CharacterRace::Human,
+                    battle_file::Race::Machine => CharacterRace::Machine,
                 },
-            )
+                hand: vec![],
+                remaining_actions: 0,
+                image: member.image.clone(),
+                deck: member
+                    .cards
+                    .iter()
+                    .map(|card_id| CardInstance {
+                        card_id: CardId::new(*card_id),
+                        card_instance_id: CardInstanceId::new(current_card_instance_id.inc()),
+                    })
+                    .collect(),
+                discard: vec![],
+                health: Health::new(member.base_health),
+                max_health: Health::new(member.max_health.unwrap_or(member.base_health)),
+                hand_size: member.hand_size.unwrap_or(battle.default_hand_size),
+                movement: 0,
+                initiative: member.initiative,
+                speed: member.speed.unwrap_or(READINESS_PER_TURN),
+                readiness: 0,
+                armor: member.armor,
+                block: 0,
+                shield: member.shield,
+                resistances: battle
+                    .resistances
+                    .get(&member.race)
+                    .into_iter()
+                    .flatten()
+                    .chain(&member.resistances)
+                    .map(|(damage_type, multiplier)| {
+                        (deserialize_damage_type(damage_type), *multiplier)
+                    })
+                    .collect(),
+                energy: member.energy,
+                max_energy: member.max_energy.or(battle.default_max_energy),
+                energy_per_turn: member
+                    .energy_per_turn
+                    .or(battle.default_energy_per_turn)
+                    .unwrap_or(0),
+                default_movement: member
+                    .movement
+                    .unwrap_or(battle.default_movement.unwrap_or(0)),
+                contains: member
+                    .contains
+                    .iter()
+                    .map(|content| match content {
+                        battle_file::Content::Card(id) => crate::Content::Card(CardInstance::new(
+                            CardId::new(*id),
+                            CardInstanceId::new(current_card_instance_id.inc()),
+                        )),
+                        battle_file::Content::Object(id) => {
+                            crate::Content::Object(ObjectInstance::new(
+                                ObjectId::new(*id),
+                                ObjectInstanceId::new(current_object_instance_id.inc()),
+                            ))
+                        }
+                    })
+                    .collect(),
+            };
+            for effect_id in &member.effects {
+                let effect = effects
+                    .get(&EffectId::new(*effect_id))
+                    .ok_or_else(|| format!("Invalid effect id {effect_id} for {}", member.name))?;
+                character.add_effect(effect, None);
+            }
+            Ok((CharacterId::new(index), character))
         })
         .collect()
 }
@@ -495,26 +719,55 @@ fn deserialize_board(
     Ok(board)
 }
 
-async fn deserialize_actors(
+/// Starts the server every player shares and joins through their own seat, unless players use the
+/// terminal
+fn start_web_server(
     battle: &battle_file::Battle,
+    server_config: &ServerConfig,
     asset_directory: Option<&Path>,
+) -> Result<Option<Arc<WebServer>>, String> {
+    let has_players = battle
+        .teams
+        .iter()
+        .flat_map(|team| &team.members)
+        .any(|member| member.is_player);
+    if !has_players || cfg!(feature = "terminal_ui") {
+        return Ok(None);
+    }
+
+    WebServer::new(server_config, asset_directory)
+        .map(Some)
+        .map_err(|err| {
+            format!(
+                "Unable to start web server on {}:{}: {err}",
+                server_config.host, server_config.port
+            )
+        })
+}
+
+async fn deserialize_actors(
+    battle: &battle_file::Battle,
+    web_server: Option<Arc<WebServer>>,
 ) -> Vec<(TeamId, Box<dyn Actor>)> {
     join_all(get_all_team_character_ids(battle).iter().map(
-        move |(team_id, character_id, team_member)| {
+        |(team_id, character_id, team_member)| {
             let is_player = team_member.is_player;
+            let ai = team_member.ai;
+            let web_server = web_server.clone();
             async move {
                 let character_id = CharacterId::new(*character_id);
                 (
                     TeamId::new((*team_id).try_into().unwrap()),
                     if is_player {
-                        if cfg!(feature = "terminal_ui") {
-                            Box::new(TerminalActor { character_id }) as Box<dyn Actor>
-                        } else {
-                            Box::new(WebActor::new(character_id, asset_directory).await.unwrap())
-                                as Box<dyn Actor>
+                        match web_server {
+                            Some(web_server) => {
+                                Box::new(WebActor::new(character_id, web_server).await)
+                                    as Box<dyn Actor>
+                            }
+                            None => Box::new(TerminalActor::new(character_id)),
                         }
                     } else {
-                        Box::new(DumbActor { character_id }) as Box<dyn Actor>
+                        create_ai_actor(character_id, ai)
                     },
                 )
             }
@@ -0,0 +1,339 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Battle, BattleText, CardId, CardInstance, CharacterId, Content, DamageType, Defense, EffectId,
+    EndConditionType, GridLocation, Pile, battle_markup,
+};
+
+/// Something that happened during a battle. The battle history is rendered from these.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub enum BattleEvent {
+    RoundStarted {
+        round: u16,
+    },
+    Passed {
+        character_id: CharacterId,
+    },
+    CardPlayed {
+        character_id: CharacterId,
+        card: CardInstance,
+        target_id: CharacterId,
+    },
+    DamageDealt {
+        source_id: CharacterId,
+        target_id: CharacterId,
+        amount: u64,
+        /// Damage absorbed by the target's defenses
+        #[serde(default)]
+        blocked: u64,
+        #[serde(default)]
+        damage_type: DamageType,
+        /// The card whose action dealt the damage. Not set for damage dealt by effects.
+        #[serde(default)]
+        card_id: Option<CardId>,
+    },
+    Healed {
+        source_id: CharacterId,
+        target_id: CharacterId,
+        amount: u64,
+    },
+    ActionsGained {
+        character_id: CharacterId,
+        amount: u64,
+    },
+    MovementGained {
+        character_id: CharacterId,
+        amount: u64,
+    },
+    EnergyGained {
+        character_id: CharacterId,
+        amount: u64,
+    },
+    EnergyLost {
+        character_id: CharacterId,
+        amount: u64,
+    },
+    CardsDrawn {
+        character_id: CharacterId,
+        count: u64,
+    },
+    CardsDiscarded {
+        character_id: CharacterId,
+        count: u64,
+    },
+    /// Cards were removed from the game
+    CardsExhausted {
+        character_id: CharacterId,
+        count: u64,
+    },
+    CardAdded {
+        character_id: CharacterId,
+        card: CardInstance,
+        pile: Pile,
+    },
+    DiscardShuffled {
+        character_id: CharacterId,
+    },
+    /// The character looked at the top cards of their deck and put them back in any order
+    DeckReordered {
+        character_id: CharacterId,
+        count: u64,
+    },
+    EffectApplied {
+        character_id: CharacterId,
+        effect_id: EffectId,
+    },
+    EffectRemoved {
+        character_id: CharacterId,
+        effect_id: EffectId,
+        count: u64,
+    },
+    DefenseGained {
+        character_id: CharacterId,
+        defense: Defense,
+        amount: u64,
+    },
+    DefenseLost {
+        character_id: CharacterId,
+        defense: Defense,
+        amount: u64,
+    },
+    /// An application of an effect ran out of turns
+    EffectExpired {
+        character_id: CharacterId,
+        effect_id: EffectId,
+    },
+    CardDestroyed {
+        character_id: CharacterId,
+        card: CardInstance,
+    },
+    Moved {
+        character_id: CharacterId,
+        from: GridLocation,
+        to: GridLocation,
+    },
+    ItemTaken {
+        character_id: CharacterId,
+        item: Content,
+    },
+    CharacterDied {
+        character_id: CharacterId,
+    },
+    TurnTimedOut {
+        character_id: CharacterId,
+    },
+    ActionUndone {
+        character_id: CharacterId,
+    },
+    BattleEnded {
+        end_state: EndConditionType,
+    },
+}
+
+impl BattleEvent {
+    /// Whether `viewer` may see the event, or a spectator when there is none. Which cards a
+    /// character got is only revealed to that character, like its hand.
+    pub fn is_visible_to(&self, viewer: Option<CharacterId>) -> bool {
+        match self {
+            Self::CardAdded { character_id, .. }
+            | Self::ItemTaken {
+                character_id,
+                item: Content::Card(_),
+            } => viewer == Some(*character_id),
+            _ => true,
+        }
+    }
+
+    /// Renders the event for the battle history. Returns `None` for events too noisy to record.
+    pub fn to_battle_text(&self, battle: &Battle) -> Option<BattleText> {
+        let name = |character_id: &CharacterId| &battle.characters[character_id].name;
+        let card_name = |card: &CardInstance| &battle.cards[&card.card_id].name;
+        let effect_name = |effect_id: &EffectId| &battle.effects[effect_id].name;
+        let defense_name = |defense: &Defense| match defense {
+            Defense::Armor => "armor",
+            Defense::Block => "block",
+            Defense::Shield => "shield",
+        };
+        let cards = |count: &u64| format!("{count} card{}", if *count != 1 { "s" } else { "" });
+
+        Some(match self {
+            Self::RoundStarted { round } => battle_markup![format!("--- Round {round}")],
+            Self::Passed { character_id } => {
+                battle_markup![@id(name(character_id)), " took no action"]
+            }
+            Self::CardPlayed {
+                character_id,
+                card,
+                target_id,
+            } => battle_markup![
+                @id(name(character_id)),
+                " used ",
+                @attack(card_name(card)),
+                " on ",
+                @id(name(target_id)),
+                ". "
+            ],
+            Self::DamageDealt {
+                target_id,
+                amount,
+                blocked,
+                damage_type,
+                ..
+            } => battle_markup![
+                @damage(amount),
+                match damage_type {
+                    DamageType::Physical => " damage to ",
+                    DamageType::Fire => " fire damage to ",
+                    DamageType::Poison => " poison damage to ",
+                    DamageType::Electric => " electric damage to ",
+                },
+                @id(name(target_id)),
+                if *blocked > 0 {
+                    format!(" ({blocked} blocked). ")
+                } else {
+                    ". ".to_string()
+                }
+            ],
+            Self::Healed {
+                target_id, amount, ..
+            } => battle_markup![@id(name(target_id)), " healed ", @damage(amount), ". "],
+            Self::ActionsGained {
+                character_id,
+                amount,
+            } => battle_markup![
+                @id(name(character_id)),
+                format!(
+                    " gained {} action{}. ",
+                    amount,
+                    if *amount != 1 { "s" } else { "" }
+                )
+            ],
+            Self::MovementGained {
+                character_id,
+                amount,
+            } => battle_markup![
+                @id(name(character_id)),
+                format!(
+                    " can move {} more space{}. ",
+                    amount,
+                    if *amount != 1 { "s" } else { "" }
+                )
+            ],
+            Self::EnergyGained {
+                character_id,
+                amount,
+            } => battle_markup![@id(name(character_id)), format!(" gained {amount} energy. ")],
+            Self::EnergyLost {
+                character_id,
+                amount,
+            } => battle_markup![@id(name(character_id)), format!(" lost {amount} energy. ")],
+            Self::CardsDrawn {
+                character_id,
+                count,
+            } => battle_markup![@id(name(character_id)), format!(" drew {}. ", cards(count))],
+            Self::CardsDiscarded {
+                character_id,
+                count,
+            } => battle_markup![@id(name(character_id)), format!(" discarded {}. ", cards(count))],
+            Self::CardsExhausted {
+                character_id,
+                count,
+            } => battle_markup![@id(name(character_id)), format!(" exhausted {}. ", cards(count))],
+            Self::CardAdded {
+                character_id,
+                card,
+                pile,
+            } => battle_markup![
+                @attack(card_name(card)),
+                " was added to ",
+                @id(name(character_id)),
+                match pile {
+                    Pile::Hand => "'s hand. ",
+                    Pile::Deck => "'s deck. ",
+                    Pile::Discard => "'s discard pile. ",
+                }
+            ],
+            Self::DiscardShuffled { character_id } => battle_markup![
+                @id(name(character_id)),
+                " shuffled their discard pile into their deck. "
+            ],
+            Self::DeckReordered {
+                character_id,
+                count,
+            } => battle_markup![
+                @id(name(character_id)),
+                format!(" reordered the top {} of their deck. ", cards(count))
+            ],
+            Self::EffectApplied {
+                character_id,
+                effect_id,
+            } => battle_markup![@id(name(character_id)), " got ", @id(effect_name(effect_id))],
+            Self::EffectRemoved {
+                character_id,
+                effect_id,
+                count,
+            } => battle_markup![
+                @id(name(character_id)),
+                if *count == 1 {
+                    " lost ".to_string()
+                } else {
+                    format!(" lost {count} ")
+                },
+                @id(effect_name(effect_id))
+            ],
+            Self::DefenseGained {
+                character_id,
+                defense,
+                amount,
+            } => battle_markup![
+                @id(name(character_id)),
+                format!(" gained {amount} {}. ", defense_name(defense))
+            ],
+            Self::DefenseLost {
+                character_id,
+                defense,
+                amount,
+            } => battle_markup![
+                @id(name(character_id)),
+                format!(" lost {amount} {}. ", defense_name(defense))
+            ],
+            Self::EffectExpired {
+                character_id,
+                effect_id,
+            } => battle_markup![
+                @id(effect_name(effect_id)),
+                " on ",
+                @id(name(character_id)),
+                " wore off."
+            ],
+            Self::CardDestroyed { card, .. } => {
+                battle_markup![@id(card_name(card)), " was destroyed."]
+            }
+            Self::Moved { .. } => return None,
+            Self::ItemTaken { character_id, item } => battle_markup![
+                @id(name(character_id)),
+                " took ",
+                @id(match item {
+                    Content::Card(card) => card_name(card),
+                    Content::Object(object) => &battle.objects[&object.object_id].name,
+                }),
+                ". "
+            ],
+            Self::CharacterDied { character_id } => {
+                battle_markup![@id(name(character_id)), " died."]
+            }
+            Self::TurnTimedOut { character_id } => {
+                battle_markup![@id(name(character_id)), " ran out of time."]
+            }
+            Self::ActionUndone { character_id } => {
+                battle_markup![@id(name(character_id)), " took back their last action."]
+            }
+            Self::BattleEnded { end_state } => match end_state {
+                EndConditionType::Win => battle_markup!["Congratulations, you won."],
+                EndConditionType::Loss => battle_markup!["Sorry, you lost."],
+            },
+        })
+    }
+}
@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -20,6 +20,10 @@ pub struct Battle {
     pub introduction: Option<StoryCard>,
     pub default_hand_size: HandSize,
     pub default_movement: Option<u64>,
+    /// Most energy a character can hold. Unlimited when not set.
+    pub default_max_energy: Option<u64>,
+    /// Energy each character gains at the start of its turn
+    pub default_energy_per_turn: Option<u64>,
     #[serde(default)]
     pub effects: Vec<Effect>,
     #[serde(default)]
@@ -29,6 +33,69 @@ pub struct Battle {
     #[serde(default)]
     pub end_conditions: Vec<EndCondition>,
     pub teams: Vec<Team>,
+    /// Where the web server for players is hosted. Command line arguments take precedence.
+    #[serde(default)]
+    pub server: ServerOptions,
+    /// Seconds each character has for their turn. Unlimited when not set.
+    pub turn_time_limit: Option<u64>,
+    /// What happens to the rest of a turn that runs out of time
+    #[serde(default)]
+    pub on_turn_timeout: TurnTimeout,
+    /// The order characters take their turns in each round
+    #[serde(default)]
+    pub turn_order: TurnOrder,
+    /// Rolled every round and added to the initiative of each character with the `initiative`
+    /// turn order
+    pub initiative_roll: Option<MaybeU64Range>,
+    /// Multipliers for the damage each race takes of each type, e.g. 0 for immune or 2 for
+    /// vulnerable. Types not listed deal their full damage.
+    #[serde(default)]
+    pub resistances: HashMap<Race, HashMap<DamageType, f64>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnOrder {
+    /// Every member of the first team, then every member of the second team and so on
+    #[default]
+    TeamByTeam,
+    /// The first member of each team, then the second member of each team and so on
+    Interleaved,
+    /// Highest initiative first
+    Initiative,
+    /// Faster characters take more turns. A speed of 10 is one turn per round.
+    Speed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnTimeout {
+    /// The character passes
+    #[default]
+    Pass,
+    /// The character's `ai`, or the basic AI when it has none, plays the rest of the turn
+    Ai,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ServerOptions {
+    pub host: Option<String>,
+    /// 0 binds to any free port
+    pub port: Option<u16>,
+    /// Directory to serve the web client from instead of the built one
+    pub static_directory: Option<String>,
+}
+
+impl ServerOptions {
+    /// Fills in options not set here from `fallback`
+    pub fn or(self, fallback: ServerOptions) -> ServerOptions {
+        ServerOptions {
+            host: self.host.or(fallback.host),
+            port: self.port.or(fallback.port),
+            static_directory: self.static_directory.or(fallback.static_directory),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
@@ -80,6 +147,16 @@ impl Battle {
                     CardAction::RemoveEffect { target, .. } => target,
                     CardAction::ReduceEffect { target, .. } => target,
                     CardAction::DestroySelf { .. } => &Target::Me,
+                    CardAction::GainDefense { target, .. } => target,
+                    CardAction::StripDefense { target, .. } => target,
+                    CardAction::GainEnergy { target, .. } => target,
+                    CardAction::LoseEnergy { target, .. } => target,
+                    CardAction::Draw { target, .. } => target,
+                    CardAction::Discard { target, .. } => target,
+                    CardAction::Exhaust { target, .. } => target,
+                    CardAction::AddCard { target, .. } => target,
+                    CardAction::ShuffleDiscard { target } => target,
+                    CardAction::ReorderDeck { target, .. } => target,
                 };
                 if target != &Target::Me && card.range.is_none() {
                     return Err(format!(
@@ -90,14 +167,57 @@ impl Battle {
             }
         }
 
-        let mut player_found = false;
-        for team in &battle.teams {
-            for team_member in &team.members {
-                if team_member.is_player {
-                    if player_found {
-                        Err("Multiple playable team members found.")?;
-                    }
-                    player_found = true;
+        for action in battle
+            .cards
+            .iter()
+            .flat_map(|card| &card.actions)
+            .chain(battle.effects.iter().flat_map(|effect| &effect.actions))
+        {
+            if let CardAction::AddCard { card, .. } = action
+                && *card >= battle.cards.len()
+            {
+                return Err(format!("Card {card} to add does not exist"));
+            }
+        }
+
+        for effect in &battle.effects {
+            if effect.max_stacks == Some(0) {
+                return Err(format!(
+                    "Effect {} must have max_stacks above 0",
+                    effect.name
+                ));
+            }
+        }
+
+        for member in battle.teams.iter().flat_map(|team| &team.members) {
+            if member.speed == Some(0) {
+                return Err(format!(
+                    "Team member {} must have a speed above 0",
+                    member.name
+                ));
+            }
+            for effect_id in &member.effects {
+                if *effect_id >= battle.effects.len() {
+                    return Err(format!(
+                        "Team member {} has effect {effect_id} that does not exist",
+                        member.name
+                    ));
+                }
+            }
+        }
+
+        for resistances in battle.resistances.values().chain(
+            battle
+                .teams
+                .iter()
+                .flat_map(|team| &team.members)
+                .map(|member| &member.resistances),
+        ) {
+            for (damage_type, multiplier) in resistances {
+                if !multiplier.is_finite() || *multiplier < 0.0 {
+                    return Err(format!(
+                        "Resistance to {damage_type:?} damage must be a multiplier of 0 or more, not {multiplier}"
+                    ));
                 }
             }
         }
@@ -222,12 +342,53 @@ pub struct TeamMember {
     pub hand_size: Option<HandSize>,
     #[serde(default)]
     pub is_player: bool,
+    /// How the character behaves when not controlled by a player
+    pub ai: Option<AiPersonality>,
+    /// Overrides the battle's `turn_time_limit` for this character
+    pub turn_time_limit: Option<u64>,
+    #[serde(default)]
+    pub initiative: u64,
+    pub speed: Option<u64>,
+    #[serde(default)]
+    pub armor: u64,
+    /// Shield the character starts with
+    #[serde(default)]
+    pub shield: u64,
+    /// Multipliers for the damage the character takes of each type, replacing those of its race
+    #[serde(default)]
+    pub resistances: HashMap<DamageType, f64>,
+    /// Energy the character starts with
+    #[serde(default)]
+    pub energy: u64,
+    /// Overrides the battle's `default_max_energy` for this character
+    pub max_energy: Option<u64>,
+    /// Overrides the battle's `default_energy_per_turn` for this character
+    pub energy_per_turn: Option<u64>,
     pub image: String,
     pub location: (usize, usize),
     pub movement: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum AiPersonality {
+    /// Charges the nearest enemy and hits the weakest one in range as hard as possible
+    Aggressive,
+    /// Stays with its allies and heals itself when badly hurt
+    Defensive,
+    /// Keeps allies healed from behind the front line and only attacks when nobody needs healing
+    Healer,
+    /// Runs away once badly hurt and never approaches enemies
+    Coward,
+    /// Never moves, attacking whatever comes in range
+    Stationary,
+    /// Picks any legal action at random
+    Random,
+    /// Plans ahead by simulating its options
+    Lookahead,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
 #[serde(deny_unknown_fields)]
 pub enum Race {
     Human,
@@ -261,6 +422,26 @@ pub struct Effect {
     pub image: String,
     pub triggers: Option<Vec<Trigger>>,
     pub actions: Vec<CardAction>,
+    /// Turns of the affected character the effect lasts for after it was last applied. Permanent
+    /// when not set.
+    pub duration: Option<u64>,
+    /// Most stacks the effect can have. Unlimited when not set.
+    pub max_stacks: Option<u64>,
+    /// What applying the effect to a character that already has it does
+    #[serde(default)]
+    pub stacking: Stacking,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Stacking {
+    /// Adds a stack, which scales the amounts of the effect's actions, and restarts its duration
+    #[default]
+    Add,
+    /// Only restarts the duration
+    Refresh,
+    /// Leaves the effect as it is
+    Ignore,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
@@ -268,6 +449,16 @@ pub struct Effect {
 pub enum Trigger {
     Death,
     TurnStart,
+    TurnEnd,
+    RoundStart,
+    RoundEnd,
+    /// The affected character took damage. Actions targeting others target the attacker.
+    OnDamaged,
+    /// The affected character was healed. Actions targeting others target the healer.
+    OnHeal,
+    /// The affected character played a card. Actions targeting others target the card's target.
+    OnCardPlayed,
+    OnMove,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
@@ -277,6 +468,8 @@ pub enum CardAction {
         target: Target,
         amount: MaybeU64Range,
         area: Option<MaybeU64Range>,
+        #[serde(default)]
+        damage_type: DamageType,
     },
     Heal {
         target: Target,
@@ -310,6 +503,86 @@ pub enum CardAction {
     DestroySelf {
         chance: Option<f64>,
     },
+    GainDefense {
+        target: Target,
+        defense: Defense,
+        amount: MaybeU64Range,
+    },
+    /// Takes away the defense, all of it when no amount is given
+    StripDefense {
+        target: Target,
+        defense: Defense,
+        amount: Option<MaybeU64Range>,
+    },
+    GainEnergy {
+        target: Target,
+        amount: MaybeU64Range,
+    },
+    LoseEnergy {
+        target: Target,
+        amount: MaybeU64Range,
+    },
+    Draw {
+        target: Target,
+        amount: MaybeU64Range,
+    },
+    /// Discards cards from hand, chosen by the target unless random
+    Discard {
+        target: Target,
+        amount: MaybeU64Range,
+        #[serde(default)]
+        random: bool,
+    },
+    /// Removes cards in hand from the game, chosen by the target unless random
+    Exhaust {
+        target: Target,
+        amount: MaybeU64Range,
+        #[serde(default)]
+        random: bool,
+    },
+    AddCard {
+        target: Target,
+        card: CardId,
+        pile: Pile,
+    },
+    ShuffleDiscard {
+        target: Target,
+    },
+    /// Lets the target look at the top cards of its deck and put them back in any order
+    ReorderDeck {
+        target: Target,
+        amount: MaybeU64Range,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Pile {
+    Hand,
+    /// At a random position
+    Deck,
+    Discard,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum DamageType {
+    #[default]
+    Physical,
+    Fire,
+    Poison,
+    Electric,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Defense {
+    /// Reduces the damage of every hit
+    Armor,
+    /// Absorbs damage until the start of the character's next turn
+    Block,
+    /// Absorbs damage until used up
+    Shield,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -321,6 +594,8 @@ pub struct Card {
     pub flavor: Option<String>,
     pub actions: Vec<CardAction>,
     pub range: Option<u64>,
+    /// Energy it takes to play the card, on top of an action
+    pub cost: Option<u64>,
 }
 
 impl Display for Card {
@@ -429,6 +704,7 @@ mod tests {
                 target: Target::Others,
                 amount: MaybeU64Range::Absolute(123),
                 area: Some(MaybeU64Range::Absolute(2)),
+                damage_type: DamageType::Physical,
             }
         );
 
@@ -436,7 +712,49 @@ mod tests {
     }
 
     #[test]
-    fn test_multi_player_error() {
+    fn test_unknown_member_effect() {
+        let data = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 1,
+            "board": { "width": 1, "height": 1 },
+            "cards": [],
+            "effects": [
+                {
+                    "id": 3,
+                    "name": "Misplaced",
+                    "description": "Effects are found by their position, not their id",
+                    "triggers": ["death"],
+                    "image": "effect.png",
+                    "actions": []
+                }
+            ],
+            "teams": [
+                {
+                    "name": "Team A",
+                    "members": [
+                        {
+                            "name": "Member 1",
+                            "race": "Human",
+                            "base_health": 10,
+                            "cards": [],
+                            "effects": [3],
+                            "location": [0, 0],
+                            "image": "player.png"
+                        }
+                    ]
+                }
+            ]
+        }"#;
+
+        assert_eq!(
+            Battle::parse_from_str(data).unwrap_err(),
+            "Team member Member 1 has effect 3 that does not exist"
+        );
+    }
+
+    #[test]
+    fn test_multiple_players() {
         let data = r#"{
             "title": "Example Game",
             "description": "Example Description",
@@ -470,12 +788,42 @@ mod tests {
             ]
         }"#;
 
-        let maybe_battle = Battle::parse_from_str(data);
+        let battle = Battle::parse_from_str(data).unwrap();
+
+        assert_eq!(
+            battle.teams[0]
+                .members
+                .iter()
+                .filter(|member| member.is_player)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_server_options() {
+        let data = r#"{
+            "title": "Example Game",
+            "description": "Example Description",
+            "default_hand_size": 5,
+            "board": { "width": 1, "height": 1 },
+            "server": { "host": "127.0.0.1", "port": 0 },
+            "teams": []
+        }"#;
+
+        let battle = Battle::parse_from_str(data).unwrap();
+        let command_line = ServerOptions {
+            port: Some(9000),
+            ..Default::default()
+        };
 
-        assert!(maybe_battle.is_err());
         assert_eq!(
-            maybe_battle.unwrap_err(),
-            "Multiple playable team members found."
+            command_line.or(battle.server),
+            ServerOptions {
+                host: Some("127.0.0.1".to_string()),
+                port: Some(9000),
+                static_directory: None,
+            }
         );
     }
 
@@ -4,6 +4,7 @@ use crate::*;
 
 pub enum BattleMenuOutput {
     Pass,
+    Undo,
     Card {
         target: CharacterId,
         card: CardInstance,
@@ -34,8 +35,32 @@ impl MenuItem<Battle, BattleMenuOutput> for CardSelectionItem {
 
 pub struct ActionsMenu {
     pub me: CharacterId,
-    pub cards: Vec<CardInstance>,
-    pub targets: Vec<CharacterId>,
+    /// Only cards and targets that appear in these actions are offered
+    pub legal_actions: Vec<Action>,
+}
+
+impl ActionsMenu {
+    fn cards(&self) -> Vec<CardInstance> {
+        let mut cards = vec![];
+        for action in &self.legal_actions {
+            if let Action::Act(card, _) = action
+                && !cards.contains(card)
+            {
+                cards.push(*card);
+            }
+        }
+        cards
+    }
+
+    fn targets(&self, card: &CardInstance) -> Vec<CharacterId> {
+        self.legal_actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::Act(legal_card, target) if legal_card == card => Some(*target),
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 impl MenuItem<Battle, BattleMenuOutput> for ActionsMenu {
@@ -43,27 +68,15 @@ impl MenuItem<Battle, BattleMenuOutput> for ActionsMenu {
         "Cards".to_string()
     }
 
-    fn action(&self, battle: &Battle) -> BattleMenuAction {
+    fn action(&self, _battle: &Battle) -> BattleMenuAction {
         BattleMenuAction::MenuItem(
-            self.cards
+            self.cards()
                 .iter()
                 .map(|card| -> Rc<dyn MenuItem<Battle, BattleMenuOutput>> {
-                    let card_target = battle.cards[&card.card_id].target();
                     Rc::new(CardMenu {
                         me: self.me,
                         card: *card,
-                        targets: self
-                            .targets
-                            .iter()
-                            .filter(|target| {
-                                if target == &&self.me {
-                                    card_target.is_super_set(&Target::Me)
-                                } else {
-                                    card_target.is_super_set(&Target::Others)
-                                }
-                            })
-                            .copied()
-                            .collect(),
+                        targets: self.targets(card),
                     })
                 })
                 .collect(),
@@ -77,22 +90,25 @@ pub struct CardMenu {
     pub targets: Vec<CharacterId>,
 }
 
+fn card_label(card: &Card) -> String {
+    format!(
+        "{}: {}{}{}{}",
+        card.name,
+        termion::style::Bold,
+        card.description,
+        termion::style::Reset,
+        card.flavor.as_ref().map_or(String::new(), |flavor| format!(
+            " - {}{}{}",
+            termion::style::Italic,
+            flavor,
+            termion::style::Reset
+        ))
+    )
+}
+
 impl MenuItem<Battle, BattleMenuOutput> for CardMenu {
     fn label(&self, battle: &Battle) -> String {
-        let card = &battle.cards[&self.card.card_id];
-        format!(
-            "{}: {}{}{}{}",
-            card.name,
-            termion::style::Bold,
-            card.description,
-            termion::style::Reset,
-            card.flavor.as_ref().map_or(String::new(), |flavor| format!(
-                " - {}{}{}",
-                termion::style::Italic,
-                flavor,
-                termion::style::Reset
-            ))
-        )
+        card_label(&battle.cards[&self.card.card_id])
     }
 
     fn action(&self, battle: &Battle) -> BattleMenuAction {
@@ -127,3 +143,30 @@ impl MenuItem<Battle, BattleMenuOutput> for PassMenuItem {
         MenuAction::Done(BattleMenuOutput::Pass)
     }
 }
+
+pub struct UndoMenuItem {}
+
+impl MenuItem<Battle, BattleMenuOutput> for UndoMenuItem {
+    fn label(&self, _battle: &Battle) -> String {
+        "Undo".to_string()
+    }
+
+    fn action(&self, _battle: &Battle) -> BattleMenuAction {
+        MenuAction::Done(BattleMenuOutput::Undo)
+    }
+}
+
+/// One of the cards offered by a `CardChoice`
+pub struct CardPickItem {
+    pub card: CardInstance,
+}
+
+impl MenuItem<Battle, CardInstance> for CardPickItem {
+    fn label(&self, battle: &Battle) -> String {
+        card_label(&battle.cards[&self.card.card_id])
+    }
+
+    fn action(&self, _battle: &Battle) -> MenuAction<Battle, CardInstance> {
+        MenuAction::Done(self.card)
+    }
+}
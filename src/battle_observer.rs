@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use crate::{ActionLogEntry, Battle, BattleEvent, CharacterId, EndConditionType};
+
+/// Receives notifications about the lifecycle of a battle without controlling any character.
+/// All methods default to doing nothing so observers only implement what they need.
+#[allow(unused_variables)]
+pub trait BattleObserver {
+    fn on_round_start(&self, battle: &Battle) {}
+
+    fn on_round_end(&self, battle: &Battle) {}
+
+    fn on_turn_start(&self, battle: &Battle, character_id: CharacterId) {}
+
+    fn on_turn_end(&self, battle: &Battle, character_id: CharacterId) {}
+
+    /// Called for every action submitted by an actor, whether or not it was accepted
+    fn on_action(&self, battle: &Battle, entry: &ActionLogEntry) {}
+
+    fn on_event(&self, battle: &Battle, event: &BattleEvent) {}
+
+    /// Called after each check of the end conditions with the result, if any was met
+    fn on_end_condition_evaluated(&self, battle: &Battle, end_state: Option<EndConditionType>) {}
+}
+
+/// Allows the creator of an observer to keep a handle to it, e.g. to read collected metrics
+impl<T: BattleObserver> BattleObserver for Arc<T> {
+    fn on_round_start(&self, battle: &Battle) {
+        self.as_ref().on_round_start(battle)
+    }
+
+    fn on_round_end(&self, battle: &Battle) {
+        self.as_ref().on_round_end(battle)
+    }
+
+    fn on_turn_start(&self, battle: &Battle, character_id: CharacterId) {
+        self.as_ref().on_turn_start(battle, character_id)
+    }
+
+    fn on_turn_end(&self, battle: &Battle, character_id: CharacterId) {
+        self.as_ref().on_turn_end(battle, character_id)
+    }
+
+    fn on_action(&self, battle: &Battle, entry: &ActionLogEntry) {
+        self.as_ref().on_action(battle, entry)
+    }
+
+    fn on_event(&self, battle: &Battle, event: &BattleEvent) {
+        self.as_ref().on_event(battle, event)
+    }
+
+    fn on_end_condition_evaluated(&self, battle: &Battle, end_state: Option<EndConditionType>) {
+        self.as_ref().on_end_condition_evaluated(battle, end_state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        ActionLogEntry, Battle, BattleEvent, BattleObserver, CharacterId, DumbActor,
+        EndConditionType, SeededRandomProvider,
+    };
+
+    #[derive(Default)]
+    struct LifecycleRecorder {
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl LifecycleRecorder {
+        fn record(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+    }
+
+    impl BattleObserver for LifecycleRecorder {
+        fn on_round_start(&self, battle: &Battle) {
+            self.record(format!("round_start {}", battle.round));
+        }
+
+        fn on_round_end(&self, battle: &Battle) {
+            self.record(format!("round_end {}", battle.round));
+        }
+
+        fn on_turn_start(&self, _battle: &Battle, character_id: CharacterId) {
+            self.record(format!("turn_start {character_id}"));
+        }
+
+        fn on_turn_end(&self, _battle: &Battle, character_id: CharacterId) {
+            self.record(format!("turn_end {character_id}"));
+        }
+
+        fn on_action(&self, _battle: &Battle, entry: &ActionLogEntry) {
+            self.record(format!("action {}", entry.character_id));
+        }
+
+        fn on_event(&self, _battle: &Battle, _event: &BattleEvent) {
+            self.record("event".to_string());
+        }
+
+        fn on_end_condition_evaluated(
+            &self,
+            _battle: &Battle,
+            end_state: Option<EndConditionType>,
+        ) {
+            if let Some(end_state) = end_state {
+                self.record(format!("end {end_state:?}"));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observer_receives_lifecycle() -> Result<(), String> {
+        let mut battle = Battle::deserialize_with_actors(
+            include_str!("../data/poison-escape.json"),
+            None,
+            Box::new(SeededRandomProvider::new(11)),
+            |id, _| Box::new(DumbActor { character_id: id }),
+        )?;
+        let recorder = Arc::new(LifecycleRecorder::default());
+        battle.add_observer(Box::new(recorder.clone()));
+        battle.run_to_completion().await.unwrap();
+
+        let calls = recorder.calls.lock().unwrap();
+        assert_eq!(calls[0], "round_start 1");
+        assert_eq!(calls[1], "event");
+        assert_eq!(calls[2], "turn_start 0");
+        assert_eq!(
+            calls.iter().filter(|call| *call == "event").count(),
+            battle.events.len()
+        );
+        assert_eq!(
+            calls
+                .iter()
+                .filter(|call| call.starts_with("action"))
+                .count(),
+            battle.action_log.len()
+        );
+        assert_eq!(
+            calls
+                .iter()
+                .filter(|call| call.starts_with("turn_start"))
+                .count(),
+            calls
+                .iter()
+                .filter(|call| call.starts_with("turn_end"))
+                .count()
+        );
+
+        let end_call = format!("end {:?}", battle.end_state.unwrap());
+        let end_index = calls.iter().position(|call| *call == end_call).unwrap();
+        assert_eq!(
+            calls[end_index + 1..],
+            [
+                format!(
+                    "turn_end {}",
+                    battle.action_log.last().unwrap().character_id
+                ),
+                format!("round_end {}", battle.round),
+                "event".to_string(),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Action, Battle, CharacterId, EndConditionType, Health, RandomPick, ReplayActor,
+    ReplayRandomProvider, map_serde_error,
+};
+
+/// A single action submitted by an actor during a battle
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ActionLogEntry {
+    pub round: u16,
+    pub character_id: CharacterId,
+    pub action: Action,
+    /// Whether the battle accepted the action
+    pub accepted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CharacterOutcome {
+    pub character_id: CharacterId,
+    pub health: Health,
+}
+
+/// Summary of the state a battle ended in, used to verify replays
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BattleOutcome {
+    pub end_state: Option<EndConditionType>,
+    pub round: u16,
+    pub characters: Vec<CharacterOutcome>,
+}
+
+impl BattleOutcome {
+    pub fn from_battle(battle: &Battle) -> Self {
+        let mut characters: Vec<CharacterOutcome> = battle
+            .characters
+            .values()
+            .map(|character| CharacterOutcome {
+                character_id: character.id,
+                health: character.health,
+            })
+            .collect();
+        characters.sort_by_key(|outcome| outcome.character_id);
+
+        Self {
+            end_state: battle.end_state,
+            round: battle.round,
+            characters,
+        }
+    }
+}
+
+/// Everything needed to deterministically re-run a battle from its battle file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BattleReplay {
+    pub random_picks: Vec<RandomPick>,
+    pub actions: Vec<ActionLogEntry>,
+    pub outcome: BattleOutcome,
+}
+
+impl BattleReplay {
+    /// Captures a replay of a finished battle. `random_picks` must contain every value produced by
+    /// the battle's random provider, typically collected by a `RecordingRandomProvider`.
+    pub fn new(battle: &Battle, random_picks: Vec<RandomPick>) -> Self {
+        Self {
+            random_picks,
+            actions: battle.action_log.clone(),
+            outcome: BattleOutcome::from_battle(battle),
+        }
+    }
+
+    pub fn parse_from_str(data: &str) -> Result<Self, String> {
+        serde_json::from_str(data).map_err(|err| map_serde_error(data, err))
+    }
+
+    /// Re-runs the battle described by `battle_data` using the recorded actions and random values,
+    /// returning an error if the battle does not play out identically.
+    pub async fn run(&self, battle_data: &str) -> Result<Battle, String> {
+        let mut battle = Battle::deserialize_with_actors(
+            battle_data,
+            None,
+            Box::new(ReplayRandomProvider::new(self.random_picks.clone())),
+            |character_id, _team_member| {
+                Box::new(ReplayActor::new(
+                    character_id,
+                    self.actions
+                        .iter()
+                        .filter(|entry| entry.character_id == character_id)
+                        .map(|entry| entry.action.clone())
+                        .collect(),
+                ))
+            },
+        )?;
+
+        if let Err(exit_code) = battle.run_to_completion().await {
+            return Err(format!(
+                "Replay exited early with {exit_code:?} after {} of {} recorded actions",
+                battle.action_log.len(),
+                self.actions.len()
+            ));
+        }
+
+        if let Some((index, (expected, actual))) = self
+            .actions
+            .iter()
+            .zip(&battle.action_log)
+            .enumerate()
+            .find(|(_, (expected, actual))| expected != actual)
+        {
+            return Err(format!(
+                "Replay diverged at action {index}. Expected {expected:?} but found {actual:?}"
+            ));
+        }
+        if self.actions.len() != battle.action_log.len() {
+            return Err(format!(
+                "Replay performed {} actions but {} were recorded",
+                battle.action_log.len(),
+                self.actions.len()
+            ));
+        }
+
+        let outcome = BattleOutcome::from_battle(&battle);
+        if outcome != self.outcome {
+            return Err(format!(
+                "Replay ended in a different state. Expected {:?} but found {:?}",
+                self.outcome, outcome
+            ));
+        }
+
+        Ok(battle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Battle, BattleReplay, DumbActor, Health, RecordingRandomProvider, SeededRandomProvider,
+    };
+
+    async fn record(battle_data: &str, seed: u64) -> Result<BattleReplay, String> {
+        let recorder = RecordingRandomProvider::new(Box::new(SeededRandomProvider::new(seed)));
+        let log = recorder.log();
+        let mut battle =
+            Battle::deserialize_with_actors(battle_data, None, Box::new(recorder), |id, _| {
+                Box::new(DumbActor { character_id: id })
+            })?;
+        battle.run_to_completion().await.unwrap();
+
+        let picks = log.lock().unwrap().clone();
+        Ok(BattleReplay::new(&battle, picks))
+    }
+
+    #[tokio::test]
+    async fn test_replay_data_files() -> Result<(), String> {
+        for battle_data in [
+            include_str!("../data/sample-battle.json"),
+            include_str!("../data/poison-escape.json"),
+        ] {
+            let replay = record(battle_data, 3).await?;
+            assert!(replay.outcome.end_state.is_some());
+            assert!(!replay.actions.is_empty());
+
+            let serialized = serde_json::to_string(&replay).unwrap();
+            let replay = BattleReplay::parse_from_str(&serialized)?;
+            replay.run(battle_data).await?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replay_detects_different_outcome() -> Result<(), String> {
+        let battle_data = include_str!("../data/poison-escape.json");
+        let mut replay = record(battle_data, 5).await?;
+        replay.outcome.characters[0].health += Health::new(1);
+
+        let Err(error) = replay.run(battle_data).await else {
+            panic!("Replay should not match");
+        };
+        assert!(error.starts_with("Replay ended in a different state"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replay_reports_running_out_of_actions() -> Result<(), String> {
+        let battle_data = include_str!("../data/poison-escape.json");
+        let mut replay = record(battle_data, 5).await?;
+        replay.actions.pop();
+        let recorded = replay.actions.len();
+
+        let Err(error) = replay.run(battle_data).await else {
+            panic!("Replay should not match");
+        };
+        assert!(error.starts_with("Replay exited early"));
+        assert!(error.ends_with(&format!("after {recorded} of {recorded} recorded actions")));
+        Ok(())
+    }
+}
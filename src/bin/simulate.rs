@@ -0,0 +1,429 @@
+use cpd::*;
+use std::{
+    collections::HashMap,
+    env, fs,
+    num::NonZeroUsize,
+    path::PathBuf,
+    process::ExitCode,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+#[derive(Clone, Copy)]
+enum Ai {
+    /// The AI each team member is given in the battle file. Players are controlled by `DumbActor`.
+    File,
+    Dumb,
+    Lookahead,
+}
+
+struct Args {
+    file: String,
+    ai: Ai,
+    runs: u64,
+    seed: Option<u64>,
+    /// Battles still going after this many rounds are stopped and counted as unfinished
+    max_rounds: u16,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut file = None;
+    let mut ai = Ai::File;
+    let mut runs = 100;
+    let mut seed = None;
+    let mut max_rounds = 100;
+    let mut args = args.skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ai" => {
+                ai = match args.next().ok_or("--ai requires a value")?.as_str() {
+                    "file" => Ai::File,
+                    "dumb" => Ai::Dumb,
+                    "lookahead" => Ai::Lookahead,
+                    other => return Err(format!("Unknown AI \"{other}\"")),
+                };
+            }
+            "--runs" => {
+                let value = args.next().ok_or("--runs requires a value")?;
+                runs = value
+                    .parse()
+                    .map_err(|err| format!("Invalid run count \"{value}\": {err}"))?;
+            }
+            "--seed" => {
+                let value = args.next().ok_or("--seed requires a value")?;
+                seed = Some(
+                    value
+                        .parse()
+                        .map_err(|err| format!("Invalid seed \"{value}\": {err}"))?,
+                );
+            }
+            "--max-rounds" => {
+                let value = args.next().ok_or("--max-rounds requires a value")?;
+                max_rounds = value
+                    .parse()
+                    .map_err(|err| format!("Invalid round limit \"{value}\": {err}"))?;
+            }
+            flag if flag.starts_with("--") => return Err(format!("Unknown argument: {flag}")),
+            _ => {
+                if file.replace(arg).is_some() {
+                    return Err("Only one battle file may be specified".to_string());
+                }
+            }
+        }
+    }
+
+    Ok(Args {
+        file: file.ok_or("A battle file must be specified")?,
+        ai,
+        runs,
+        seed,
+        max_rounds,
+    })
+}
+
+#[derive(Default)]
+struct CardStatistics {
+    plays: u64,
+    damage: u64,
+}
+
+#[derive(Default)]
+struct Statistics {
+    battles: u64,
+    wins: u64,
+    losses: u64,
+    unfinished: u64,
+    rounds: u64,
+    cards: HashMap<CardId, CardStatistics>,
+    /// Damage dealt outside of a card being played, e.g. by effects triggering at turn start
+    other_damage: u64,
+    turns: u64,
+    /// Number of turns started with each effect applied
+    effect_turns: HashMap<EffectId, u64>,
+}
+
+impl Statistics {
+    fn merge(&mut self, other: Statistics) {
+        self.battles += other.battles;
+        self.wins += other.wins;
+        self.losses += other.losses;
+        self.unfinished += other.unfinished;
+        self.rounds += other.rounds;
+        for (card_id, card) in other.cards {
+            let entry = self.cards.entry(card_id).or_default();
+            entry.plays += card.plays;
+            entry.damage += card.damage;
+        }
+        self.other_damage += other.other_damage;
+        self.turns += other.turns;
+        for (effect_id, turns) in other.effect_turns {
+            *self.effect_turns.entry(effect_id).or_default() += turns;
+        }
+    }
+}
+
+#[derive(Default)]
+struct StatisticsObserver {
+    statistics: Mutex<Statistics>,
+}
+
+impl BattleObserver for StatisticsObserver {
+    fn on_turn_start(&self, battle: &Battle, character_id: CharacterId) {
+        let mut statistics = self.statistics.lock().unwrap();
+        statistics.turns += 1;
+        for instance in &battle.characters[&character_id].effects {
+            *statistics
+                .effect_turns
+                .entry(instance.effect_id)
+                .or_default() += 1;
+        }
+    }
+
+    fn on_event(&self, _battle: &Battle, event: &BattleEvent) {
+        let mut statistics = self.statistics.lock().unwrap();
+        match event {
+            BattleEvent::CardPlayed { card, .. } => {
+                statistics.cards.entry(card.card_id).or_default().plays += 1;
+            }
+            BattleEvent::DamageDealt {
+                amount, card_id, ..
+            } => match card_id {
+                Some(card_id) => statistics.cards.entry(*card_id).or_default().damage += amount,
+                None => statistics.other_damage += amount,
+            },
+            _ => {}
+        }
+    }
+}
+
+fn simulate(battle_data: &str, ai: Ai, seed: u64, max_rounds: u16) -> Result<Statistics, String> {
+    let mut battle = Battle::deserialize_with_actors(
+        battle_data,
+        Some(PathBuf::from("data")),
+        Box::new(SeededRandomProvider::new(seed)),
+        |character_id, team_member| -> Box<dyn Actor> {
+            match ai {
+                Ai::File if team_member.is_player => Box::new(DumbActor { character_id }),
+                Ai::File => create_ai_actor(character_id, team_member.ai),
+                Ai::Dumb => Box::new(DumbActor { character_id }),
+                Ai::Lookahead => Box::new(LookaheadActor::new(
+                    character_id,
+                    seed.wrapping_add(character_id.id as u64),
+                )),
+            }
+        },
+    )?;
+    // AIs answer straight away, and the executor below has no timer to enforce limits with
+    battle.turn_timers.clear();
+    let observer = Arc::new(StatisticsObserver::default());
+    battle.add_observer(Box::new(observer.clone()));
+
+    futures::executor::block_on(async {
+        while battle.end_state.is_none() && battle.round < max_rounds {
+            battle.advance().await?;
+        }
+        Ok::<(), ExitCode>(())
+    })
+    .map_err(|exit_code| format!("Battle with seed {seed} exited with {exit_code:?}"))?;
+
+    let (end_state, rounds) = (battle.end_state, battle.round);
+    drop(battle);
+    let mut statistics = Arc::into_inner(observer)
+        .expect("Observer should only be held by the battle")
+        .statistics
+        .into_inner()
+        .unwrap();
+    statistics.battles = 1;
+    statistics.rounds = rounds.into();
+    match end_state {
+        Some(EndConditionType::Win) => statistics.wins = 1,
+        Some(EndConditionType::Loss) => statistics.losses = 1,
+        None => statistics.unfinished = 1,
+    }
+    Ok(statistics)
+}
+
+fn percent(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    }
+}
+
+fn report(battle: &Battle, statistics: &Statistics) {
+    let battles = statistics.battles;
+    println!("Battles: {battles}");
+    println!(
+        "Wins: {} ({:.1}%)",
+        statistics.wins,
+        percent(statistics.wins, battles)
+    );
+    println!(
+        "Losses: {} ({:.1}%)",
+        statistics.losses,
+        percent(statistics.losses, battles)
+    );
+    if statistics.unfinished > 0 {
+        println!(
+            "Unfinished: {} ({:.1}%)",
+            statistics.unfinished,
+            percent(statistics.unfinished, battles)
+        );
+    }
+    println!(
+        "Average rounds: {:.2}",
+        statistics.rounds as f64 / battles.max(1) as f64
+    );
+
+    println!();
+    println!("Damage per card:");
+    let mut cards: Vec<_> = statistics.cards.iter().collect();
+    cards.sort_by_key(|(card_id, _)| **card_id);
+    for (card_id, card) in cards {
+        println!(
+            "  {}: {} damage over {} plays ({:.2} per play)",
+            battle.cards[card_id].name,
+            card.damage,
+            card.plays,
+            card.damage as f64 / card.plays.max(1) as f64
+        );
+    }
+    println!("  Other sources: {} damage", statistics.other_damage);
+
+    println!();
+    println!("Effect uptime (share of turns started with the effect):");
+    let mut effects: Vec<_> = battle.effects.keys().collect();
+    effects.sort();
+    for effect_id in effects {
+        let turns = statistics.effect_turns.get(effect_id).copied().unwrap_or(0);
+        println!(
+            "  {}: {:.1}%",
+            battle.effects[effect_id].name,
+            percent(turns, statistics.turns)
+        );
+    }
+}
+
+fn main() -> Result<(), ExitCode> {
+    let args = parse_args(env::args()).map_err(|err| {
+        eprintln!("{err}");
+        ExitCode::FAILURE
+    })?;
+    let file_path = format!("data/{}", args.file);
+    let battle_data = fs::read_to_string(&file_path).map_err(|err| {
+        eprintln!("Unable to open file {file_path}: {err}");
+        ExitCode::FAILURE
+    })?;
+    // Used to validate the file up front and to look up names for the report
+    let battle = Battle::deserialize_with_actors(
+        &battle_data,
+        None,
+        Box::<DefaultRandomProvider>::default(),
+        |character_id, _| Box::new(DumbActor { character_id }),
+    )
+    .map_err(|err| {
+        eprintln!("Unable to load {file_path}: {err}");
+        ExitCode::FAILURE
+    })?;
+
+    let base_seed = args
+        .seed
+        .unwrap_or_else(|| SeededRandomProvider::from_random_seed().seed());
+    println!(
+        "Simulating {} battles of {} from seed {base_seed}",
+        args.runs, args.file
+    );
+
+    let threads = thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1) as u64;
+    let mut statistics = Statistics::default();
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|thread_index| {
+                let battle_data = &battle_data;
+                let ai = args.ai;
+                let max_rounds = args.max_rounds;
+                let runs = args.runs;
+                scope.spawn(move || -> Result<Statistics, String> {
+                    let mut statistics = Statistics::default();
+                    for run in (thread_index..runs).step_by(threads as usize) {
+                        statistics.merge(simulate(
+                            battle_data,
+                            ai,
+                            base_seed.wrapping_add(run),
+                            max_rounds,
+                        )?);
+                    }
+                    Ok(statistics)
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            match handle.join().expect("Simulation thread panicked") {
+                Ok(thread_statistics) => statistics.merge(thread_statistics),
+                Err(err) => {
+                    eprintln!("{err}");
+                    return Err(ExitCode::FAILURE);
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    println!();
+    report(&battle, &statistics);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A hero next to a mine that explodes when it dies
+    const MINE_BATTLE: &str = r#"{
+        "title": "Mine",
+        "description": "Poke the mine",
+        "default_hand_size": 1,
+        "board": { "width": 3, "height": 3 },
+        "effects": [
+            {
+                "id": 0,
+                "name": "Explode",
+                "description": "Deal damage to surroundings upon death",
+                "image": "mine.png",
+                "triggers": ["death"],
+                "actions": [{ "type": "damage", "target": "self", "amount": 5, "area": 1 }]
+            }
+        ],
+        "cards": [
+            {
+                "id": 0,
+                "name": "Poke",
+                "description": "Deal 1 damage",
+                "range": 1,
+                "actions": [{ "type": "damage", "target": "others", "amount": 1 }]
+            }
+        ],
+        "teams": [
+            {
+                "name": "World",
+                "members": [
+                    {
+                        "name": "Mine",
+                        "ai": "stationary",
+                        "race": "Machine",
+                        "image": "mine.png",
+                        "base_health": 1,
+                        "effects": [0],
+                        "cards": [0],
+                        "location": [1, 1]
+                    }
+                ]
+            },
+            {
+                "name": "Hero",
+                "members": [
+                    {
+                        "name": "Hero",
+                        "is_player": true,
+                        "race": "Human",
+                        "image": "captain.png",
+                        "base_health": 10,
+                        "cards": [0],
+                        "location": [1, 2]
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_death_trigger_damage_is_not_card_damage() -> Result<(), String> {
+        let mut battle = Battle::deserialize_with_actors(
+            MINE_BATTLE,
+            None,
+            Box::new(SeededRandomProvider::new(1)),
+            |character_id, _| Box::new(DumbActor { character_id }),
+        )?;
+        let observer = Arc::new(StatisticsObserver::default());
+        battle.add_observer(Box::new(observer.clone()));
+        let (mine, hero) = (CharacterId::new(0), CharacterId::new(1));
+        let poke = battle.characters[&hero].deck[0];
+        let character = battle.characters.get_mut(&hero).unwrap();
+        character.hand.push(poke);
+        character.remaining_actions = 1;
+
+        assert_eq!(battle.handle_action(&hero, Action::Act(poke, mine)), Ok(()));
+        assert!(battle.characters[&mine].is_dead());
+        assert_eq!(battle.characters[&hero].health, Health::new(5));
+
+        let statistics = observer.statistics.lock().unwrap();
+        assert_eq!(statistics.cards[&poke.card_id].plays, 1);
+        assert_eq!(statistics.cards[&poke.card_id].damage, 1);
+        assert_eq!(statistics.other_damage, 5);
+        Ok(())
+    }
+}
@@ -1,9 +1,9 @@
 use schemars::JsonSchema;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{CardInstance, CharacterId, Grid, GridDimension, GridLocation};
 
-#[derive(Serialize, PartialEq, Debug, JsonSchema)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, JsonSchema)]
 #[serde(deny_unknown_fields, tag = "type", content = "id")]
 pub enum BoardItem {
     Character(CharacterId),
@@ -11,7 +11,7 @@ pub enum BoardItem {
     Inert,
 }
 
-#[derive(Serialize, JsonSchema)]
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Board {
     pub grid: Grid<BoardItem>,
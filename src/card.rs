@@ -1,11 +1,11 @@
-use crate::{DeclareWrappedType, EffectId, RandomProvider, battle_file};
+use crate::{DamageType, DeclareWrappedType, Defense, EffectId, RandomProvider, battle_file};
 use schemars::JsonSchema;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 DeclareWrappedType!(CardId, id, battle_file::CardId);
 DeclareWrappedType!(CardInstanceId, id, usize);
 
-#[derive(PartialEq, Copy, Clone, Eq, Hash, Debug, Serialize, JsonSchema)]
+#[derive(PartialEq, Copy, Clone, Eq, Hash, Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct CardInstance {
     pub card_id: CardId,
@@ -39,9 +39,13 @@ impl U64Range {
     pub fn resolve(&self, random_provider: &dyn RandomProvider) -> LifeNumber {
         random_provider.pick_linear_u64(self.0, self.1)
     }
+
+    pub fn scaled(&self, factor: u64) -> Self {
+        U64Range(self.0.saturating_mul(factor), self.1.saturating_mul(factor))
+    }
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize, JsonSchema)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub enum Target {
     Me,
@@ -56,6 +60,14 @@ impl Target {
     }
 }
 
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub enum Pile {
+    Hand,
+    Deck,
+    Discard,
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub enum CardAction {
@@ -63,6 +75,7 @@ pub enum CardAction {
         target: Target,
         amount: U64Range,
         area: U64Range,
+        damage_type: DamageType,
     },
     Heal {
         target: Target,
@@ -96,6 +109,54 @@ pub enum CardAction {
     DestroySelf {
         chance: Chance,
     },
+    GainDefense {
+        target: Target,
+        defense: Defense,
+        amount: U64Range,
+    },
+    /// Takes away the defense, all of it when there is no amount
+    StripDefense {
+        target: Target,
+        defense: Defense,
+        amount: Option<U64Range>,
+    },
+    GainEnergy {
+        target: Target,
+        amount: U64Range,
+    },
+    LoseEnergy {
+        target: Target,
+        amount: U64Range,
+    },
+    Draw {
+        target: Target,
+        amount: U64Range,
+    },
+    /// Discards cards from hand, chosen by the target unless random
+    Discard {
+        target: Target,
+        amount: U64Range,
+        random: bool,
+    },
+    /// Removes cards in hand from the game, chosen by the target unless random
+    Exhaust {
+        target: Target,
+        amount: U64Range,
+        random: bool,
+    },
+    AddCard {
+        target: Target,
+        card: CardId,
+        pile: Pile,
+    },
+    ShuffleDiscard {
+        target: Target,
+    },
+    /// Lets the target look at the top cards of its deck and put them back in any order
+    ReorderDeck {
+        target: Target,
+        amount: U64Range,
+    },
 }
 
 impl CardAction {
@@ -109,7 +170,41 @@ impl CardAction {
             Self::RemoveEffect { target, .. } => target,
             Self::ReduceEffect { target, .. } => target,
             Self::DestroySelf { .. } => &Target::Me,
+            Self::GainDefense { target, .. } => target,
+            Self::StripDefense { target, .. } => target,
+            Self::GainEnergy { target, .. } => target,
+            Self::LoseEnergy { target, .. } => target,
+            Self::Draw { target, .. } => target,
+            Self::Discard { target, .. } => target,
+            Self::Exhaust { target, .. } => target,
+            Self::AddCard { target, .. } => target,
+            Self::ShuffleDiscard { target } => target,
+            Self::ReorderDeck { target, .. } => target,
+        }
+    }
+
+    /// The action with its amounts multiplied, e.g. by the stacks of the effect running it
+    pub fn scaled(&self, factor: u64) -> Self {
+        let mut action = self.clone();
+        match &mut action {
+            Self::Damage { amount, .. }
+            | Self::Heal { amount, .. }
+            | Self::GainAction { amount, .. }
+            | Self::Move { amount, .. }
+            | Self::GainDefense { amount, .. }
+            | Self::GainEnergy { amount, .. }
+            | Self::LoseEnergy { amount, .. }
+            | Self::Draw { amount, .. }
+            | Self::Discard { amount, .. }
+            | Self::Exhaust { amount, .. }
+            | Self::ReorderDeck { amount, .. }
+            | Self::StripDefense {
+                amount: Some(amount),
+                ..
+            } => *amount = amount.scaled(factor),
+            _ => (),
         }
+        action
     }
 }
 
@@ -122,6 +217,8 @@ pub struct Card {
     pub flavor: Option<String>,
     pub actions: Vec<CardAction>,
     pub range: u64,
+    /// Energy it takes to play the card, on top of an action
+    pub cost: u64,
 }
 
 impl Card {
@@ -0,0 +1,61 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{CardInstance, CharacterId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub enum CardChoiceKind {
+    Discard,
+    Exhaust,
+    Reorder,
+}
+
+/// Cards a character has to pick from before the battle goes on. For a reorder all of them are
+/// picked, in the order they go back on top of the deck.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CardChoice {
+    pub character_id: CharacterId,
+    pub kind: CardChoiceKind,
+    pub cards: Vec<CardInstance>,
+    pub count: usize,
+}
+
+impl CardChoice {
+    /// The first cards offered, picked when the character makes no valid choice
+    pub fn default_pick(&self) -> Vec<CardInstance> {
+        self.cards.iter().take(self.count).copied().collect()
+    }
+
+    /// Checks that `picked` is `count` different cards out of those offered
+    pub fn is_valid(&self, picked: &[CardInstance]) -> bool {
+        picked.len() == self.count
+            && picked
+                .iter()
+                .enumerate()
+                .all(|(index, card)| self.cards.contains(card) && !picked[..index].contains(card))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CardChoice, CardChoiceKind, CardId, CardInstance, CardInstanceId, CharacterId};
+
+    #[test]
+    fn test_is_valid() {
+        let card = |id| CardInstance::new(CardId::new(0), CardInstanceId::new(id));
+        let choice = CardChoice {
+            character_id: CharacterId::new(0),
+            kind: CardChoiceKind::Discard,
+            cards: vec![card(0), card(1), card(2)],
+            count: 2,
+        };
+
+        assert!(choice.is_valid(&choice.default_pick()));
+        assert!(choice.is_valid(&[card(2), card(0)]));
+        assert!(!choice.is_valid(&[card(2)]));
+        assert!(!choice.is_valid(&[card(1), card(1)]));
+        assert!(!choice.is_valid(&[card(1), card(3)]));
+    }
+}
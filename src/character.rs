@@ -1,10 +1,11 @@
 use std::{
     cmp::min,
+    collections::HashMap,
     ops::{Sub, SubAssign},
 };
 
 use schemars::JsonSchema;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::*;
 
@@ -12,7 +13,7 @@ type HandSize = usize;
 
 DeclareWrappedType!(CharacterId, id, usize);
 
-#[derive(Serialize, JsonSchema)]
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub enum CharacterRace {
     Human,
@@ -37,12 +38,39 @@ impl SubAssign<Attack> for Health {
     }
 }
 
-#[derive(Serialize, JsonSchema)]
+/// Protection that absorbs damage before health. Armor reduces the damage of every hit, block
+/// absorbs damage until the start of the character's next turn and shield until it is used up.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub enum Defense {
+    Armor,
+    Block,
+    Shield,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub enum DamageType {
+    #[default]
+    Physical,
+    Fire,
+    Poison,
+    Electric,
+}
+
+/// How much of an attack got through to health and how much was absorbed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DamageTaken {
+    pub dealt: u64,
+    pub blocked: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Character {
     pub id: CharacterId,
     pub name: String,
-    pub effects: Vec<EffectId>,
+    pub effects: Vec<EffectInstance>,
     pub race: CharacterRace,
     pub hand: Vec<CardInstance>,
     pub deck: Vec<CardInstance>,
@@ -55,6 +83,26 @@ pub struct Character {
     pub image: String,
     pub movement: u64,
     pub default_movement: u64,
+    pub initiative: u64,
+    pub speed: u64,
+    /// Progress towards the next turn with the speed turn order
+    pub readiness: u64,
+    #[serde(default)]
+    pub armor: u64,
+    #[serde(default)]
+    pub block: u64,
+    #[serde(default)]
+    pub shield: u64,
+    /// Multiplier for the damage taken of each type, 1 for those not listed
+    #[serde(default)]
+    pub resistances: HashMap<DamageType, f64>,
+    #[serde(default)]
+    pub energy: u64,
+    /// Unlimited when not set
+    #[serde(default)]
+    pub max_energy: Option<u64>,
+    #[serde(default)]
+    pub energy_per_turn: u64,
 }
 
 impl Character {
@@ -69,13 +117,31 @@ impl Character {
             0
         };
 
-        if self.deck.len() < cards_to_draw {
+        self.draw(cards_to_draw, random_provider);
+    }
+
+    /// Moves up to `count` cards from the top of the deck to the hand, shuffling the discard pile
+    /// under the deck first if it runs short. Returns how many were drawn.
+    pub fn draw(&mut self, count: usize, random_provider: &dyn RandomProvider) -> usize {
+        if self.deck.len() < count {
             self.deck.extend(self.discard.shuffle(random_provider));
             self.discard.clear();
         }
 
-        self.hand
-            .extend(self.deck.drain(..min(self.deck.len(), cards_to_draw)));
+        let drawn = min(self.deck.len(), count);
+        self.hand.extend(self.deck.drain(..drawn));
+        drawn
+    }
+
+    /// Shuffles the discard pile together with the deck
+    pub fn shuffle_discard(&mut self, random_provider: &dyn RandomProvider) {
+        self.deck.append(&mut self.discard);
+        self.deck = self
+            .deck
+            .shuffle(random_provider)
+            .into_iter()
+            .copied()
+            .collect();
     }
 
     pub fn get_default_turn_actions(&self) -> Option<u64> {
@@ -89,9 +155,214 @@ impl Character {
     pub fn reach_distance(&self) -> GridDimension {
         1
     }
+
+    pub fn defense(&self, defense: Defense) -> u64 {
+        match defense {
+            Defense::Armor => self.armor,
+            Defense::Block => self.block,
+            Defense::Shield => self.shield,
+        }
+    }
+
+    pub fn defense_mut(&mut self, defense: Defense) -> &mut u64 {
+        match defense {
+            Defense::Armor => &mut self.armor,
+            Defense::Block => &mut self.block,
+            Defense::Shield => &mut self.shield,
+        }
+    }
+
+    /// Adds energy up to the maximum and returns how much was added
+    pub fn gain_energy(&mut self, amount: u64) -> u64 {
+        let energy = min(
+            self.energy.saturating_add(amount),
+            self.max_energy.unwrap_or(u64::MAX).max(self.energy),
+        );
+        let gained = energy - self.energy;
+        self.energy = energy;
+        gained
+    }
+
+    pub fn effect_stacks(&self, effect_id: EffectId) -> u64 {
+        self.effects
+            .iter()
+            .find(|instance| instance.effect_id == effect_id)
+            .map_or(0, |instance| instance.stacks)
+    }
+
+    /// Applies the effect following its stacking and returns whether that changed anything
+    pub fn add_effect(&mut self, effect: &Effect, source: Option<CharacterId>) -> bool {
+        let Some(instance) = self
+            .effects
+            .iter_mut()
+            .find(|instance| instance.effect_id == effect.id)
+        else {
+            self.effects.push(EffectInstance {
+                effect_id: effect.id,
+                stacks: 1,
+                remaining_turns: effect.duration,
+                source,
+            });
+            return true;
+        };
+
+        match effect.stacking {
+            Stacking::Ignore => return false,
+            Stacking::Refresh => (),
+            Stacking::Add => {
+                instance.stacks = min(instance.stacks + 1, effect.max_stacks.unwrap_or(u64::MAX))
+            }
+        }
+        instance.remaining_turns = effect.duration;
+        instance.source = source;
+        true
+    }
+
+    /// Takes up to `count` stacks off the effect, removing it once none are left, and returns how
+    /// many were taken
+    pub fn reduce_effect(&mut self, effect_id: EffectId, count: u64) -> u64 {
+        let Some(index) = self
+            .effects
+            .iter()
+            .position(|instance| instance.effect_id == effect_id)
+        else {
+            return 0;
+        };
+        let instance = &mut self.effects[index];
+        let removed = min(instance.stacks, count);
+        instance.stacks -= removed;
+        if instance.stacks == 0 {
+            self.effects.remove(index);
+        }
+        removed
+    }
+
+    /// Scales damage of the given type by the character's resistance to it
+    pub fn resist(&self, damage_type: DamageType, damage: u64) -> u64 {
+        match self.resistances.get(&damage_type) {
+            Some(multiplier) => (damage as f64 * multiplier).round() as u64,
+            None => damage,
+        }
+    }
+
+    /// Armor reduces the attack, then block and shield absorb what they can before the rest is
+    /// taken from health
+    pub fn take_damage(&mut self, attack: Attack) -> DamageTaken {
+        let mut remaining = attack.damage.saturating_sub(self.armor);
+        for pool in [&mut self.block, &mut self.shield] {
+            let absorbed = min(*pool, remaining);
+            *pool -= absorbed;
+            remaining -= absorbed;
+        }
+        self.health -= Attack::new(remaining);
+        DamageTaken {
+            dealt: remaining,
+            blocked: attack.damage - remaining,
+        }
+    }
 }
 
 #[derive(Clone)]
 pub enum CharacterAction {
     Attack { name: String, base_damage: i64 },
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Attack, CharacterId, DamageTaken, Effect, EffectId, Stacking};
+
+    use super::Character;
+
+    fn new_character(armor: u64, block: u64, shield: u64) -> Character {
+        serde_json::from_value(serde_json::json!({
+            "id": 0,
+            "name": "Knight",
+            "effects": [],
+            "race": "Human",
+            "hand": [],
+            "deck": [],
+            "discard": [],
+            "health": 10,
+            "max_health": 10,
+            "remaining_actions": 0,
+            "hand_size": 0,
+            "contains": [],
+            "image": "",
+            "movement": 0,
+            "default_movement": 0,
+            "initiative": 0,
+            "speed": 10,
+            "readiness": 0,
+            "armor": armor,
+            "block": block,
+            "shield": shield,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_take_damage() {
+        let mut character = new_character(1, 2, 3);
+        assert_eq!(
+            character.take_damage(Attack::new(4)),
+            DamageTaken {
+                dealt: 0,
+                blocked: 4
+            }
+        );
+        assert_eq!((character.block, character.shield), (0, 2));
+
+        assert_eq!(
+            character.take_damage(Attack::new(6)),
+            DamageTaken {
+                dealt: 3,
+                blocked: 3
+            }
+        );
+        assert_eq!(character.shield, 0);
+        assert_eq!(character.health.health, 7);
+
+        // Armor never heals
+        assert_eq!(character.take_damage(Attack::new(0)).dealt, 0);
+    }
+
+    fn new_effect(stacking: Stacking) -> Effect {
+        Effect {
+            id: EffectId::new(0),
+            name: "Burn".to_string(),
+            description: String::new(),
+            image: String::new(),
+            actions: vec![],
+            triggers: vec![],
+            duration: Some(2),
+            max_stacks: Some(3),
+            stacking,
+        }
+    }
+
+    #[test]
+    fn test_add_effect() {
+        let burn = new_effect(Stacking::Add);
+        let mut character = new_character(0, 0, 0);
+        for _ in 0..4 {
+            assert!(character.add_effect(&burn, Some(CharacterId::new(1))));
+        }
+        assert_eq!(character.effect_stacks(burn.id), 3);
+        assert_eq!(character.reduce_effect(burn.id, 2), 2);
+        assert_eq!(character.reduce_effect(burn.id, 2), 1);
+        assert!(character.effects.is_empty());
+
+        let refreshed = new_effect(Stacking::Refresh);
+        character.add_effect(&refreshed, None);
+        character.effects[0].remaining_turns = Some(1);
+        assert!(character.add_effect(&refreshed, Some(CharacterId::new(1))));
+        assert_eq!(character.effects[0].stacks, 1);
+        assert_eq!(character.effects[0].remaining_turns, Some(2));
+        assert_eq!(character.effects[0].source, Some(CharacterId::new(1)));
+
+        let ignored = new_effect(Stacking::Ignore);
+        character.effects[0].remaining_turns = Some(1);
+        assert!(!character.add_effect(&ignored, None));
+        assert_eq!(character.effects[0].remaining_turns, Some(1));
+    }
+}
@@ -22,6 +22,7 @@ fn total_average_damage(card: &Card) -> u64 {
                 target: _,
                 amount,
                 area: _, // TODO: Evaluate area damage
+                damage_type: _,
             } => (amount.0 + amount.1) / 2,
             _ => 0,
         })
@@ -58,38 +59,40 @@ impl Actor for DumbActor {
             .get_team_for_actor(self)
             .unwrap_or_else(|| panic!("Failed to find team for self {}", self.character_id));
         let character = battle.get_character(self);
+        let legal_actions = battle.legal_actions(self.character_id);
 
         let prioritized_cards = prioritize_cards(character, battle);
         for card_instance in prioritized_cards {
             let card = &battle.cards[&card_instance.card_id];
-            if (card.target() == Target::Me
-                && total_average_damage(card) < battle.characters[&self.character_id].health.health)
-                || (card.target() == Target::Any && total_average_damage(card) == 0)
+            let self_action = Action::Act(card_instance, self.character_id);
+            if legal_actions.contains(&self_action)
+                && ((card.target() == Target::Me
+                    && total_average_damage(card) < character.health.health)
+                    || (card.target() == Target::Any && total_average_damage(card) == 0))
             {
-                return Ok(Action::Act(card_instance, self.character_id));
+                return Ok(self_action);
             }
 
             for (team_id, actor) in &battle.actors {
                 let opponent = &battle.characters[actor.get_character_id()];
-                if &my_team != team_id
-                    && !opponent.is_dead()
-                    && let Some(distance) = battle.board.distance(
-                        BoardItem::Character(character.id),
-                        BoardItem::Character(opponent.id),
-                    )
+                if &my_team == team_id || opponent.is_dead() {
+                    continue;
+                }
+
+                let attack = Action::Act(card_instance, opponent.id);
+                if legal_actions.contains(&attack) {
+                    return Ok(attack);
+                }
+
+                // Only try moving if there's more than 2 spots (current location and target location)
+                if let Some(path) = battle.board.shortest_path(
+                    BoardItem::Character(character.id),
+                    BoardItem::Character(opponent.id),
+                ) && path.len() > 2
                 {
-                    if card.range >= distance && character.remaining_actions > 0 {
-                        return Ok(Action::Act(card_instance, opponent.id));
-                    } else if character.movement > 0
-                        && let Some(path) = battle.board.shortest_path(
-                            BoardItem::Character(character.id),
-                            BoardItem::Character(opponent.id),
-                        )
-                    {
-                        // Only try moving if there's more than 2 spots (current location and target location)
-                        if path.len() > 2 {
-                            return Ok(Action::Move(character.id, path[1].clone()));
-                        }
+                    let step = Action::Move(character.id, path[1].clone());
+                    if legal_actions.contains(&step) {
+                        return Ok(step);
                     }
                 }
             }
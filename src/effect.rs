@@ -1,15 +1,34 @@
 use schemars::JsonSchema;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{CardAction, DeclareWrappedType, Target, battle_file};
+use crate::{CardAction, CharacterId, DeclareWrappedType, Target, battle_file};
 
 DeclareWrappedType!(EffectId, id, battle_file::EffectId);
 
-#[derive(Debug, Clone, Serialize, PartialEq, JsonSchema)]
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub enum Trigger {
     Death,
     TurnStart,
+    TurnEnd,
+    RoundStart,
+    RoundEnd,
+    OnDamaged,
+    OnHeal,
+    OnCardPlayed,
+    OnMove,
+}
+
+impl Trigger {
+    /// Whether the trigger reacts to something happening to the character. Effects run by a
+    /// reaction cannot set off further reactions, so characters cannot retaliate against each
+    /// other forever.
+    pub fn is_reaction(&self) -> bool {
+        matches!(
+            self,
+            Self::OnDamaged | Self::OnHeal | Self::OnCardPlayed | Self::OnMove
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, JsonSchema)]
@@ -21,6 +40,33 @@ pub struct Effect {
     pub image: String,
     pub actions: Vec<CardAction>,
     pub triggers: Vec<Trigger>,
+    /// Turns the effect lasts for after it was last applied, if it is not permanent
+    pub duration: Option<u64>,
+    pub max_stacks: Option<u64>,
+    pub stacking: Stacking,
+}
+
+/// What applying an effect does to a character that already has it. Add gives it another stack,
+/// Refresh only restarts its duration and Ignore leaves it as it is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub enum Stacking {
+    #[default]
+    Add,
+    Refresh,
+    Ignore,
+}
+
+/// An effect on a character. Its actions are scaled by its stacks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EffectInstance {
+    pub effect_id: EffectId,
+    pub stacks: u64,
+    /// Turns of the affected character left until the effect wears off, if it is not permanent
+    pub remaining_turns: Option<u64>,
+    /// The character that last applied the effect, if any
+    pub source: Option<CharacterId>,
 }
 
 impl Effect {
@@ -1,11 +1,11 @@
 use std::collections::VecDeque;
 
 use schemars::JsonSchema;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 pub type GridDimension = usize;
 
-#[derive(Serialize, JsonSchema)]
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Grid<T> {
     members: Vec<Vec<Option<T>>>,
@@ -13,7 +13,8 @@ pub struct Grid<T> {
     height: GridDimension,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct GridLocation {
     pub x: GridDimension,
     pub y: GridDimension,
@@ -87,6 +88,11 @@ impl<T> Grid<T> {
         self.height
     }
 
+    /// Every item on the grid, row by row
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.members.iter().flatten().flatten()
+    }
+
     pub fn find<F>(&self, predicate: F) -> Option<(GridDimension, GridDimension)>
     where
         F: Fn(&T) -> bool,
@@ -113,8 +119,8 @@ impl<T> Grid<T> {
         F: Fn(&T) -> bool,
     {
         let mut ret = vec![];
-        for x in location.x - range..=location.x + range {
-            for y in location.y - range..=location.y + range {
+        for x in location.x.saturating_sub(range)..=location.x + range {
+            for y in location.y.saturating_sub(range)..=location.y + range {
                 if location.distance(&GridLocation { x, y }) > range {
                     continue;
                 }
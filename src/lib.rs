@@ -4,23 +4,32 @@ pub mod action;
 pub mod actor;
 pub mod battle;
 mod battle_deserialize;
+pub mod battle_event;
 pub mod battle_file;
 pub mod battle_history;
 pub mod battle_menu;
+pub mod battle_observer;
+pub mod battle_replay;
 pub mod board;
 pub mod card;
+pub mod card_choice;
 pub mod character;
 pub mod content;
 pub mod deserialization;
 pub mod dumb_actor;
 pub mod effect;
 pub mod grid;
+pub mod lookahead_actor;
 pub mod menu;
 pub mod object;
+pub mod personality_actor;
 pub mod random_provider;
+pub mod replay_actor;
+pub mod save_game;
 pub mod template;
 pub mod terminal_actor;
 pub mod terminal_ui;
+pub mod undo;
 pub mod utils;
 pub mod web_actor;
 pub mod wrapped_type;
@@ -28,20 +37,29 @@ pub mod wrapped_type;
 pub use action::*;
 pub use actor::*;
 pub use battle::*;
+pub use battle_event::*;
 pub use battle_history::*;
 pub use battle_menu::*;
+pub use battle_observer::*;
+pub use battle_replay::*;
 pub use board::*;
 pub use card::*;
+pub use card_choice::*;
 pub use character::*;
 pub use content::*;
 pub use deserialization::*;
 pub use dumb_actor::*;
 pub use effect::*;
 pub use grid::*;
+pub use lookahead_actor::*;
 pub use menu::*;
 pub use object::*;
+pub use personality_actor::*;
 pub use random_provider::*;
+pub use replay_actor::*;
+pub use save_game::*;
 pub use template::*;
 pub use terminal_actor::*;
 pub use terminal_ui::*;
+pub use undo::*;
 pub use utils::*;
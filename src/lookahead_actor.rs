@@ -0,0 +1,383 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use async_trait::async_trait;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::*;
+
+/// Score of a character being alive, on top of their remaining health
+const ALIVE_VALUE: f64 = 5.0;
+/// Score of fully meeting an end condition that favors the actor's team
+const CONDITION_VALUE: f64 = 20.0;
+/// Score of the battle ending in the actor team's favor
+const END_VALUE: f64 = 100.0;
+/// Penalty per space between the actor and what it is heading for
+const DISTANCE_VALUE: f64 = 0.1;
+
+/// An AI that tries out its legal actions on copies of the battle and picks the one with the best
+/// expected score. Random outcomes are drawn from its own generator rather than the battle's
+/// random provider, so thinking ahead does not change the battle or its replays.
+pub struct LookaheadActor {
+    pub character_id: CharacterId,
+    /// How many actions of the current turn to look ahead
+    pub depth: usize,
+    /// How many random outcomes each candidate action is simulated with
+    pub samples: usize,
+    rng: Mutex<StdRng>,
+}
+
+impl LookaheadActor {
+    pub fn new(character_id: CharacterId, seed: u64) -> Self {
+        Self {
+            character_id,
+            depth: 2,
+            samples: 4,
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    /// Plays `action` on a copy of the battle and returns the best score reachable afterwards
+    fn play(
+        &self,
+        battle: &Battle,
+        action: Action,
+        depth: usize,
+        seed: u64,
+        teams: &HashMap<CharacterId, TeamId>,
+    ) -> f64 {
+        let mut next = battle.clone_state(Box::new(SeededRandomProvider::new(seed)));
+        if next.handle_action(&self.character_id, action).is_err() {
+            return f64::MIN;
+        }
+        next.end_state = next.did_battle_reach_end_condition();
+        self.search(&next, depth - 1, seed.wrapping_add(1), teams)
+    }
+
+    fn search(
+        &self,
+        battle: &Battle,
+        depth: usize,
+        seed: u64,
+        teams: &HashMap<CharacterId, TeamId>,
+    ) -> f64 {
+        let mut best = self.score(battle, teams);
+        let character = &battle.characters[&self.character_id];
+        if depth == 0
+            || battle.end_state.is_some()
+            || character.is_dead()
+            || (character.remaining_actions == 0 && character.movement == 0)
+        {
+            return best;
+        }
+
+        for action in battle.legal_actions(self.character_id) {
+            if action != Action::Pass {
+                best = best.max(self.play(battle, action, depth, seed, teams));
+            }
+        }
+        best
+    }
+
+    /// How good the battle looks for the actor's team. Higher is better.
+    fn score(&self, battle: &Battle, teams: &HashMap<CharacterId, TeamId>) -> f64 {
+        let my_team = teams[&self.character_id];
+        let mut score = 0.0;
+
+        for character in battle.characters.values() {
+            let Some(team) = teams.get(&character.id) else {
+                continue;
+            };
+            let sign = if *team == my_team { 1.0 } else { -1.0 };
+            if character.is_dead() {
+                continue;
+            }
+
+            let mut health = character.health.health as f64;
+            for instance in &character.effects {
+                health += turn_start_health_change(&battle.effects[&instance.effect_id])
+                    * instance.stacks as f64;
+            }
+            score += sign * (ALIVE_VALUE + health.max(0.0));
+        }
+
+        for end_condition in battle.end_conditions.iter() {
+            let favor = condition_favor(&end_condition.condition, teams, my_team);
+            if battle.evaluate_condition(&end_condition.condition) {
+                score += favor * END_VALUE;
+                break;
+            }
+            score += favor * CONDITION_VALUE * condition_progress(battle, &end_condition.condition);
+        }
+
+        if let Some(distance) = self.distance_to_goal(battle, teams) {
+            score -= DISTANCE_VALUE * distance as f64;
+        }
+        score
+    }
+
+    /// Distance to the nearest living enemy, or to an object the actor needs to own
+    fn distance_to_goal(
+        &self,
+        battle: &Battle,
+        teams: &HashMap<CharacterId, TeamId>,
+    ) -> Option<u64> {
+        let me = BoardItem::Character(self.character_id);
+        let my_team = teams[&self.character_id];
+
+        for end_condition in battle.end_conditions.iter() {
+            if let EndConditionCriterion::ObjectOwned {
+                character_id,
+                object_id,
+            } = &end_condition.condition
+                && *character_id == self.character_id
+                && let Some(holder) = battle.characters.values().find(|character| {
+                    character.id != self.character_id
+                        && character.contains.iter().any(|content| {
+                            matches!(content, Content::Object(object) if object.object_id == *object_id)
+                        })
+                })
+            {
+                return battle
+                    .board
+                    .distance(me.clone(), BoardItem::Character(holder.id));
+            }
+        }
+
+        battle
+            .characters
+            .values()
+            .filter(|character| {
+                !character.is_dead()
+                    && teams
+                        .get(&character.id)
+                        .is_some_and(|team| *team != my_team)
+            })
+            .filter_map(|character| {
+                battle
+                    .board
+                    .distance(me.clone(), BoardItem::Character(character.id))
+            })
+            .min()
+    }
+}
+
+/// Expected change in health each turn from an effect's turn start actions on its bearer
+fn turn_start_health_change(effect: &Effect) -> f64 {
+    if !effect.has_trigger(Trigger::TurnStart) {
+        return 0.0;
+    }
+    effect
+        .actions
+        .iter()
+        .map(|action| match action {
+            CardAction::Damage {
+                target: Target::Me,
+                amount,
+                ..
+            } => -((amount.0 + amount.1) as f64) / 2.0,
+            CardAction::Heal {
+                target: Target::Me,
+                amount,
+                ..
+            } => (amount.0 + amount.1) as f64 / 2.0,
+            _ => 0.0,
+        })
+        .sum()
+}
+
+/// 1 if meeting the condition is good for `my_team`, -1 if it is bad
+fn condition_favor(
+    condition: &EndConditionCriterion,
+    teams: &HashMap<CharacterId, TeamId>,
+    my_team: TeamId,
+) -> f64 {
+    let is_mine = |character_id: &CharacterId| teams.get(character_id) == Some(&my_team);
+    let good = match condition {
+        EndConditionCriterion::TeamMemberDeath { ids } => !ids.iter().any(is_mine),
+        EndConditionCriterion::ObjectOwned { character_id, .. } => is_mine(character_id),
+    };
+    if good { 1.0 } else { -1.0 }
+}
+
+/// How close the condition is to being met, from 0 to 1
+fn condition_progress(battle: &Battle, condition: &EndConditionCriterion) -> f64 {
+    match condition {
+        EndConditionCriterion::TeamMemberDeath { ids } => {
+            let dead = ids
+                .iter()
+                .filter(|id| battle.characters[id].is_dead())
+                .count();
+            dead as f64 / ids.len().max(1) as f64
+        }
+        EndConditionCriterion::ObjectOwned { .. } => {
+            if battle.evaluate_condition(condition) {
+                1.0
+            } else {
+                0.0
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Actor for LookaheadActor {
+    fn get_character_id(&self) -> &CharacterId {
+        &self.character_id
+    }
+
+    async fn act(&self, battle: &Battle) -> ActionResult {
+        let legal_actions = battle.legal_actions(self.character_id);
+        if legal_actions.len() == 1 {
+            return Ok(Action::Pass);
+        }
+
+        let teams: HashMap<CharacterId, TeamId> = battle
+            .actors
+            .iter()
+            .map(|(team_id, actor)| (*actor.get_character_id(), *team_id))
+            .collect();
+        let seeds: Vec<u64> = {
+            let mut rng = self.rng.lock().unwrap();
+            (0..self.samples.max(1)).map(|_| rng.next_u64()).collect()
+        };
+
+        let mut best = (f64::MIN, Action::Pass);
+        for action in legal_actions {
+            let value = seeds
+                .iter()
+                .map(|seed| self.play(battle, action.clone(), self.depth.max(1), *seed, &teams))
+                .sum::<f64>()
+                / seeds.len() as f64;
+            if value > best.0 {
+                best = (value, action);
+            }
+        }
+        Ok(best.1)
+    }
+
+    async fn on_game_over(&self, _battle: &Battle) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Action, Battle, CardId, CharacterId, LookaheadActor, SeededRandomProvider};
+
+    const DUEL: &str = r#"{
+        "title": "Duel",
+        "description": "One hit left",
+        "default_hand_size": 2,
+        "board": { "width": 3, "height": 1 },
+        "cards": [
+            {
+                "id": 0,
+                "name": "Bandage",
+                "description": "Heal 1",
+                "range": 0,
+                "actions": [{ "type": "heal", "target": "self", "amount": 1 }]
+            },
+            {
+                "id": 1,
+                "name": "Slash",
+                "description": "Deal 2 damage",
+                "range": 1,
+                "actions": [{ "type": "damage", "target": "others", "amount": 2 }]
+            }
+        ],
+        "end_conditions": [
+            {
+                "title": "Victory",
+                "description": "B is defeated",
+                "type": "win",
+                "condition": { "type": "team_member_death", "ids": [1] }
+            },
+            {
+                "title": "Defeat",
+                "description": "A is defeated",
+                "type": "loss",
+                "condition": { "type": "team_member_death", "ids": [0] }
+            }
+        ],
+        "teams": [
+            {
+                "name": "Team A",
+                "members": [
+                    {
+                        "name": "A",
+                        "race": "Human",
+                        "base_health": 5,
+                        "cards": [0, 1],
+                        "location": [0, 0],
+                        "image": "member.png"
+                    }
+                ]
+            },
+            {
+                "name": "Team B",
+                "members": [
+                    {
+                        "name": "B",
+                        "race": "Human",
+                        "base_health": 2,
+                        "cards": [0, 1],
+                        "location": [2, 0],
+                        "image": "member.png"
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    fn new_duel(seed: u64) -> Result<Battle, String> {
+        Battle::deserialize_with_actors(
+            DUEL,
+            None,
+            Box::new(SeededRandomProvider::new(seed)),
+            |character_id, _| Box::new(LookaheadActor::new(character_id, seed)),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_moves_in_and_finishes_enemy() -> Result<(), String> {
+        let mut battle = new_duel(1)?;
+        let a = CharacterId::new(0);
+        let b = CharacterId::new(1);
+        let character = battle.characters.get_mut(&a).unwrap();
+        character.hand = character.deck.clone();
+        character.remaining_actions = 1;
+        character.movement = 1;
+        let slash = *character
+            .hand
+            .iter()
+            .find(|card| card.card_id == CardId::new(1))
+            .unwrap();
+
+        let actor = battle.require_actor(&a);
+        let action = actor.act(&battle).await.unwrap();
+        assert!(matches!(action, Action::Move(id, _) if id == a));
+        battle.handle_action(&a, action).unwrap();
+
+        let actor = battle.require_actor(&a);
+        assert_eq!(actor.act(&battle).await.unwrap(), Action::Act(slash, b));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_battles_are_reproducible() -> Result<(), String> {
+        let new_battle = || {
+            Battle::deserialize_with_actors(
+                include_str!("../data/sample-battle.json"),
+                None,
+                Box::new(SeededRandomProvider::new(4)),
+                |character_id, _| Box::new(LookaheadActor::new(character_id, 4)),
+            )
+        };
+        let mut first = new_battle()?;
+        let mut second = new_battle()?;
+        first.run_to_completion().await.unwrap();
+        second.run_to_completion().await.unwrap();
+
+        assert!(first.end_state.is_some());
+        assert_eq!(first.action_log, second.action_log);
+        Ok(())
+    }
+}
@@ -1,33 +1,185 @@
 use cpd::*;
-use std::{env, fs, ops::Deref, path::PathBuf, process::ExitCode};
+use std::{env, fs, path::PathBuf, process::ExitCode};
 #[cfg(feature = "terminal_ui")]
 use {std::io, termion::raw::IntoRawMode};
 
+struct Args {
+    file: String,
+    seed: Option<u64>,
+    /// Path to write a replay of the battle to once it completes
+    record: Option<String>,
+    /// Path of a replay to verify against the battle file instead of playing
+    replay: Option<String>,
+    /// Path to save the battle to after every round
+    save: Option<String>,
+    /// Path of a save to continue the battle from
+    resume: Option<String>,
+    /// Overrides the battle file's web server options
+    server: battle_file::ServerOptions,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut file = None;
+    let mut seed = None;
+    let mut record = None;
+    let mut replay = None;
+    let mut save = None;
+    let mut resume = None;
+    let mut server = battle_file::ServerOptions::default();
+    let mut args = args.skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                let value = args.next().ok_or("--seed requires a value")?;
+                seed = Some(
+                    value
+                        .parse::<u64>()
+                        .map_err(|err| format!("Invalid seed \"{value}\": {err}"))?,
+                );
+            }
+            "--record" => {
+                record = Some(args.next().ok_or("--record requires a path")?);
+            }
+            "--replay" => {
+                replay = Some(args.next().ok_or("--replay requires a path")?);
+            }
+            "--save" => {
+                save = Some(args.next().ok_or("--save requires a path")?);
+            }
+            "--resume" => {
+                resume = Some(args.next().ok_or("--resume requires a path")?);
+            }
+            "--host" => {
+                server.host = Some(args.next().ok_or("--host requires a value")?);
+            }
+            "--port" => {
+                let value = args.next().ok_or("--port requires a value")?;
+                server.port = Some(
+                    value
+                        .parse::<u16>()
+                        .map_err(|err| format!("Invalid port \"{value}\": {err}"))?,
+                );
+            }
+            "--static-dir" => {
+                server.static_directory = Some(args.next().ok_or("--static-dir requires a path")?);
+            }
+            flag if flag.starts_with("--") => return Err(format!("Unknown argument: {flag}")),
+            _ => {
+                if file.replace(arg).is_some() {
+                    return Err("Only one battle file may be specified".to_string());
+                }
+            }
+        }
+    }
+
+    if record.is_some() && resume.is_some() {
+        return Err("--record cannot be combined with --resume".to_string());
+    }
+
+    Ok(Args {
+        file: file.unwrap_or_else(|| "sample-battle.json".to_string()),
+        seed,
+        record,
+        replay,
+        save,
+        resume,
+        server,
+    })
+}
+
+async fn verify_replay(battle_file: &str, replay_path: &str) -> Result<(), ExitCode> {
+    let replay = fs::read_to_string(replay_path)
+        .map_err(|err| err.to_string())
+        .and_then(|data| BattleReplay::parse_from_str(&data))
+        .map_err(|err| {
+            eprintln!("Unable to read replay {replay_path}: {err}");
+            ExitCode::FAILURE
+        })?;
+
+    match replay.run(battle_file).await {
+        Ok(_) => {
+            println!("Replay matched");
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            Err(ExitCode::FAILURE)
+        }
+    }
+}
+
 #[actix_web::main]
 async fn main() -> Result<(), ExitCode> {
-    let args: Vec<String> = env::args().collect();
-    let file = args
-        .get(1)
-        .map(Deref::deref)
-        .unwrap_or("sample-battle.json");
-    let file_path = format!("data/{file}");
+    let args = parse_args(env::args()).map_err(|err| {
+        eprintln!("{err}");
+        ExitCode::FAILURE
+    })?;
+    let file_path = format!("data/{}", args.file);
 
     let battle_file = fs::read_to_string(&file_path)
         .unwrap_or_else(|_| panic!("Unable to open file: {file_path}"));
+
+    if let Some(replay_path) = &args.replay {
+        return verify_replay(&battle_file, replay_path).await;
+    }
+
+    let random_provider = match args.seed {
+        Some(seed) => SeededRandomProvider::new(seed),
+        None => SeededRandomProvider::from_random_seed(),
+    };
+    println!("Using seed {}", random_provider.seed());
+    let random_provider = RecordingRandomProvider::new(Box::new(random_provider));
+    let random_picks = random_provider.log();
+
     let mut battle = Battle::deserialize(
         &battle_file,
         Some(PathBuf::from("data")),
-        Box::<DefaultRandomProvider>::default(),
+        Box::new(random_provider),
+        args.server,
     )
     .await
-    .unwrap();
+    .map_err(|err| {
+        eprintln!("Unable to load {file_path}: {err}");
+        ExitCode::FAILURE
+    })?;
+    if let Some(resume_path) = &args.resume {
+        fs::read_to_string(resume_path)
+            .map_err(|err| err.to_string())
+            .and_then(|save| battle.load(&save))
+            .map_err(|err| {
+                eprintln!("Unable to resume from {resume_path}: {err}");
+                ExitCode::FAILURE
+            })?;
+        println!("Resuming from round {}", battle.round);
+    }
     #[cfg(feature = "terminal_ui")]
     let (_out, _err) = (
         io::stdout().into_raw_mode().unwrap(),
         io::stderr().into_raw_mode().unwrap(),
     );
+    while battle.end_state.is_none() {
+        battle.advance().await?;
+        if let Some(save_path) = &args.save {
+            battle
+                .save()
+                .and_then(|save| fs::write(save_path, save).map_err(|err| err.to_string()))
+                .map_err(|err| {
+                    eprintln!("Unable to save to {save_path}: {err}");
+                    ExitCode::FAILURE
+                })?;
+        }
+    }
     battle.run_to_completion().await?;
     println!("Game over");
 
+    if let Some(record_path) = &args.record {
+        let replay = BattleReplay::new(&battle, random_picks.lock().unwrap().clone());
+        fs::write(record_path, serde_json::to_string_pretty(&replay).unwrap()).map_err(|err| {
+            eprintln!("Unable to write replay {record_path}: {err}");
+            ExitCode::FAILURE
+        })?;
+        println!("Saved replay to {record_path}");
+    }
+
     Ok(())
 }
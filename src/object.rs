@@ -1,12 +1,12 @@
 use schemars::JsonSchema;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{DeclareWrappedType, battle_file};
 
 DeclareWrappedType!(ObjectId, id, battle_file::ObjectId);
 DeclareWrappedType!(ObjectInstanceId, id, usize);
 
-#[derive(PartialEq, Copy, Clone, Eq, Hash, Debug, Serialize, JsonSchema)]
+#[derive(PartialEq, Copy, Clone, Eq, Hash, Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct ObjectInstance {
     pub object_id: ObjectId,
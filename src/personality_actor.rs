@@ -0,0 +1,499 @@
+use async_trait::async_trait;
+use rand::{RngExt, SeedableRng, rngs::StdRng};
+
+use crate::*;
+
+pub type AiPersonality = battle_file::AiPersonality;
+
+/// Creates the AI actor for a character that is not controlled by a player
+pub fn create_ai_actor(
+    character_id: CharacterId,
+    personality: Option<AiPersonality>,
+) -> Box<dyn Actor> {
+    let rules = match personality {
+        None => return Box::new(DumbActor { character_id }),
+        Some(AiPersonality::Lookahead) => {
+            return Box::new(LookaheadActor::new(character_id, character_id.id as u64));
+        }
+        Some(AiPersonality::Aggressive) => Rules::Aggressive,
+        Some(AiPersonality::Defensive) => Rules::Defensive,
+        Some(AiPersonality::Healer) => Rules::Healer,
+        Some(AiPersonality::Coward) => Rules::Coward,
+        Some(AiPersonality::Stationary) => Rules::Stationary,
+        Some(AiPersonality::Random) => Rules::Random,
+    };
+    Box::new(PersonalityActor {
+        character_id,
+        rules,
+    })
+}
+
+/// The personalities played by `PersonalityActor`
+#[derive(Clone, Copy)]
+enum Rules {
+    Aggressive,
+    Defensive,
+    Healer,
+    Coward,
+    Stationary,
+    Random,
+}
+
+/// A simple rule based AI whose priorities are set by its personality. Created through
+/// `create_ai_actor`.
+pub struct PersonalityActor {
+    character_id: CharacterId,
+    rules: Rules,
+}
+
+fn average(range: &U64Range) -> u64 {
+    (range.0 + range.1) / 2
+}
+
+fn card_damage(card: &Card) -> u64 {
+    card.actions
+        .iter()
+        .map(|action| match action {
+            CardAction::Damage {
+                target: Target::Others | Target::Any,
+                amount,
+                ..
+            } => average(amount),
+            _ => 0,
+        })
+        .sum()
+}
+
+fn card_healing(card: &Card) -> u64 {
+    card.actions
+        .iter()
+        .map(|action| match action {
+            CardAction::Heal { amount, .. } => average(amount),
+            _ => 0,
+        })
+        .sum()
+}
+
+fn is_badly_hurt(character: &Character) -> bool {
+    character.health.health * 2 <= character.max_health.health
+}
+
+/// What the actor knows about the battle when picking an action
+struct Situation<'battle> {
+    battle: &'battle Battle,
+    me: &'battle Character,
+    legal_actions: Vec<Action>,
+    allies: Vec<&'battle Character>,
+    enemies: Vec<&'battle Character>,
+}
+
+impl<'battle> Situation<'battle> {
+    fn new(battle: &'battle Battle, character_id: CharacterId) -> Self {
+        let my_team = battle
+            .actors
+            .iter()
+            .find(|(_, actor)| *actor.get_character_id() == character_id)
+            .map(|(team_id, _)| *team_id);
+        let mut allies = vec![];
+        let mut enemies = vec![];
+        for (team_id, actor) in &battle.actors {
+            let character = &battle.characters[actor.get_character_id()];
+            if character.is_dead() {
+                continue;
+            }
+            if Some(*team_id) == my_team {
+                allies.push(character);
+            } else {
+                enemies.push(character);
+            }
+        }
+
+        Self {
+            battle,
+            me: &battle.characters[&character_id],
+            legal_actions: battle.legal_actions(character_id),
+            allies,
+            enemies,
+        }
+    }
+
+    fn distance(&self, from: CharacterId, to: CharacterId) -> Option<u64> {
+        self.battle
+            .board
+            .distance(BoardItem::Character(from), BoardItem::Character(to))
+    }
+
+    /// The legal card plays on any of `targets`, with their card
+    fn card_plays<'a>(
+        &'a self,
+        targets: &'a [&'battle Character],
+    ) -> impl Iterator<Item = (&'battle Card, &'battle Character, Action)> + 'a {
+        self.legal_actions
+            .iter()
+            .filter_map(move |action| match action {
+                Action::Act(card_instance, target_id) => targets
+                    .iter()
+                    .find(|target| target.id == *target_id)
+                    .map(|target| {
+                        (
+                            &self.battle.cards[&card_instance.card_id],
+                            *target,
+                            action.clone(),
+                        )
+                    }),
+                _ => None,
+            })
+    }
+
+    /// Hits the weakest enemy in range with the most damaging card
+    fn attack(&self) -> Option<Action> {
+        self.card_plays(&self.enemies)
+            .filter(|(card, _, _)| card_damage(card) > 0)
+            .max_by_key(|(card, target, _)| {
+                (card_damage(card), std::cmp::Reverse(target.health.health))
+            })
+            .map(|(_, _, action)| action)
+    }
+
+    /// Heals the most injured of `patients` that a card can reach
+    fn heal(&self, patients: &[&'battle Character]) -> Option<Action> {
+        self.card_plays(patients)
+            .filter(|(card, target, _)| card_healing(card) > 0 && target.health < target.max_health)
+            .max_by_key(|(card, target, _)| {
+                (
+                    target.max_health.health - target.health.health,
+                    card_healing(card),
+                )
+            })
+            .map(|(_, _, action)| action)
+    }
+
+    fn heal_self(&self) -> Option<Action> {
+        self.heal(&[self.me])
+    }
+
+    /// Takes one step along the shortest path to `target_id`
+    fn step_toward(&self, target_id: CharacterId) -> Option<Action> {
+        let path = self.battle.board.shortest_path(
+            BoardItem::Character(self.me.id),
+            BoardItem::Character(target_id),
+        )?;
+        // Only move if there's more than 2 spots (current location and target location)
+        if path.len() <= 2 {
+            return None;
+        }
+        let step = Action::Move(self.me.id, path[1].clone());
+        self.legal_actions.contains(&step).then_some(step)
+    }
+
+    fn nearest(&self, characters: &[&'battle Character]) -> Option<&'battle Character> {
+        characters
+            .iter()
+            .filter(|character| character.id != self.me.id)
+            .filter_map(|character| {
+                self.distance(self.me.id, character.id)
+                    .map(|distance| (distance, *character))
+            })
+            .min_by_key(|(distance, character)| (*distance, character.id))
+            .map(|(_, character)| character)
+    }
+
+    /// Distance from `location` to the closest enemy
+    fn enemy_distance(&self, location: &GridLocation) -> Option<GridDimension> {
+        self.enemies
+            .iter()
+            .filter_map(|enemy| self.battle.board.find(&BoardItem::Character(enemy.id)))
+            .map(|(x, y)| location.distance(&GridLocation { x, y }))
+            .min()
+    }
+
+    /// Moves to the adjacent space furthest from any enemy, if it is further than the current one
+    fn step_away(&self) -> Option<Action> {
+        let (x, y) = self.battle.board.find(&BoardItem::Character(self.me.id))?;
+        let current = self.enemy_distance(&GridLocation { x, y })?;
+        self.legal_actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::Move(_, location) => self
+                    .enemy_distance(location)
+                    .map(|distance| (distance, action)),
+                _ => None,
+            })
+            .filter(|(distance, _)| *distance > current)
+            .max_by_key(|(distance, _)| *distance)
+            .map(|(_, action)| action.clone())
+    }
+
+    fn aggressive(&self) -> Option<Action> {
+        self.attack()
+            .or_else(|| self.step_toward(self.nearest(&self.enemies)?.id))
+    }
+
+    fn defensive(&self) -> Option<Action> {
+        if is_badly_hurt(self.me)
+            && let Some(action) = self.heal_self()
+        {
+            return Some(action);
+        }
+        self.attack().or_else(|| {
+            let ally = self.nearest(&self.allies)?;
+            self.step_toward(ally.id)
+        })
+    }
+
+    fn healer(&self) -> Option<Action> {
+        if let Some(action) = self.heal(&self.allies) {
+            return Some(action);
+        }
+
+        // Walk toward whoever needs healing, but never into reach of an enemy
+        let injured: Vec<_> = self
+            .allies
+            .iter()
+            .copied()
+            .filter(|ally| ally.id != self.me.id && ally.health < ally.max_health)
+            .collect();
+        if let Some(patient) = self.nearest(&injured)
+            && let Some(Action::Move(_, location)) = self.step_toward(patient.id)
+            && self
+                .enemy_distance(&location)
+                .is_none_or(|distance| distance > 1)
+        {
+            return Some(Action::Move(self.me.id, location));
+        }
+
+        self.attack()
+    }
+
+    fn coward(&self) -> Option<Action> {
+        if is_badly_hurt(self.me) {
+            return self
+                .heal_self()
+                .or_else(|| self.step_away())
+                .or_else(|| self.attack());
+        }
+        self.attack()
+    }
+
+    fn stationary(&self) -> Option<Action> {
+        if is_badly_hurt(self.me)
+            && let Some(action) = self.heal_self()
+        {
+            return Some(action);
+        }
+        self.attack()
+    }
+
+    fn random(&self) -> Option<Action> {
+        let choices: Vec<&Action> = self
+            .legal_actions
+            .iter()
+            .filter(|action| **action != Action::Pass)
+            .collect();
+        if choices.is_empty() {
+            return None;
+        }
+
+        // Seeded from the battle rather than its random provider so replays stay valid while
+        // battles played with the same seed still play out the same way. Mixed by hand because
+        // the standard hashers may change between Rust releases.
+        let seed = [
+            self.me.id.id as u64,
+            self.battle.round.into(),
+            self.battle.action_log.len() as u64,
+            self.battle.events.len() as u64,
+        ]
+        .into_iter()
+        .fold(0, |seed: u64, value| {
+            (seed.rotate_left(23) ^ value).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        });
+        let mut rng = StdRng::seed_from_u64(seed);
+        Some(choices[rng.random_range(0..choices.len())].clone())
+    }
+}
+
+#[async_trait]
+impl Actor for PersonalityActor {
+    fn get_character_id(&self) -> &CharacterId {
+        &self.character_id
+    }
+
+    async fn act(&self, battle: &Battle) -> ActionResult {
+        let situation = Situation::new(battle, self.character_id);
+        let action = match self.rules {
+            Rules::Aggressive => situation.aggressive(),
+            Rules::Defensive => situation.defensive(),
+            Rules::Healer => situation.healer(),
+            Rules::Coward => situation.coward(),
+            Rules::Stationary => situation.stationary(),
+            Rules::Random => situation.random(),
+        };
+        Ok(action.unwrap_or(Action::Pass))
+    }
+
+    async fn on_game_over(&self, _battle: &Battle) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Action, Battle, CardId, CharacterId, DefaultRandomProvider, GridLocation, Health,
+        create_ai_actor,
+    };
+
+    const SKIRMISH: &str = r#"{
+        "title": "Skirmish",
+        "description": "Every personality at once",
+        "default_hand_size": 2,
+        "default_movement": 1,
+        "board": { "width": 6, "height": 2 },
+        "cards": [
+            {
+                "id": 0,
+                "name": "Slash",
+                "description": "Deal 2 damage",
+                "range": 1,
+                "actions": [{ "type": "damage", "target": "others", "amount": 2 }]
+            },
+            {
+                "id": 1,
+                "name": "Mend",
+                "description": "Heal 2",
+                "range": 1,
+                "actions": [{ "type": "heal", "target": "others", "amount": 2 }]
+            }
+        ],
+        "end_conditions": [
+            {
+                "title": "Victory",
+                "description": "The raid is repelled",
+                "type": "win",
+                "condition": { "type": "team_member_death", "ids": [3] }
+            },
+            {
+                "title": "Defeat",
+                "description": "The defenders fall",
+                "type": "loss",
+                "condition": { "type": "team_member_death", "ids": [0, 1, 2] }
+            }
+        ],
+        "teams": [
+            {
+                "name": "Defenders",
+                "members": [
+                    {
+                        "name": "Guard",
+                        "ai": "stationary",
+                        "race": "Human",
+                        "base_health": 8,
+                        "cards": [0, 0],
+                        "location": [0, 0],
+                        "image": "member.png"
+                    },
+                    {
+                        "name": "Medic",
+                        "ai": "healer",
+                        "race": "Human",
+                        "base_health": 8,
+                        "cards": [1, 1],
+                        "location": [1, 0],
+                        "image": "member.png"
+                    },
+                    {
+                        "name": "Runner",
+                        "ai": "coward",
+                        "race": "Human",
+                        "base_health": 8,
+                        "cards": [0, 0],
+                        "location": [2, 0],
+                        "image": "member.png"
+                    }
+                ]
+            },
+            {
+                "name": "Raiders",
+                "members": [
+                    {
+                        "name": "Brute",
+                        "ai": "aggressive",
+                        "race": "Human",
+                        "base_health": 8,
+                        "cards": [0, 0],
+                        "location": [5, 0],
+                        "image": "member.png"
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    const GUARD: CharacterId = CharacterId { id: 0 };
+    const MEDIC: CharacterId = CharacterId { id: 1 };
+    const RUNNER: CharacterId = CharacterId { id: 2 };
+    const BRUTE: CharacterId = CharacterId { id: 3 };
+
+    fn new_skirmish() -> Result<Battle, String> {
+        let mut battle = Battle::deserialize_with_actors(
+            SKIRMISH,
+            None,
+            Box::<DefaultRandomProvider>::default(),
+            |character_id, team_member| create_ai_actor(character_id, team_member.ai),
+        )?;
+        for character in battle.characters.values_mut() {
+            character.hand = character.deck.clone();
+            character.remaining_actions = 1;
+            character.movement = 1;
+        }
+        Ok(battle)
+    }
+
+    async fn act(battle: &Battle, character_id: CharacterId) -> Action {
+        battle
+            .require_actor(&character_id)
+            .act(battle)
+            .await
+            .unwrap()
+    }
+
+    fn is_play(action: &Action, card_id: usize, target_id: CharacterId) -> bool {
+        matches!(action, Action::Act(card, target)
+            if card.card_id == CardId::new(card_id) && *target == target_id)
+    }
+
+    #[tokio::test]
+    async fn test_stationary_and_aggressive() -> Result<(), String> {
+        let battle = new_skirmish()?;
+        assert_eq!(act(&battle, GUARD).await, Action::Pass);
+        assert_eq!(
+            act(&battle, BRUTE).await,
+            Action::Move(BRUTE, GridLocation { x: 4, y: 0 })
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_healer_heals_injured_ally() -> Result<(), String> {
+        let mut battle = new_skirmish()?;
+        battle.characters.get_mut(&GUARD).unwrap().health = Health::new(3);
+        assert!(is_play(&act(&battle, MEDIC).await, 1, GUARD));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_coward_flees_when_hurt() -> Result<(), String> {
+        let mut battle = new_skirmish()?;
+        battle.board.grid.clear(5, 0);
+        battle
+            .board
+            .grid
+            .set(3, 0, crate::BoardItem::Character(BRUTE));
+        assert!(is_play(&act(&battle, RUNNER).await, 0, BRUTE));
+
+        battle.characters.get_mut(&RUNNER).unwrap().health = Health::new(2);
+        assert_eq!(
+            act(&battle, RUNNER).await,
+            Action::Move(RUNNER, GridLocation { x: 2, y: 1 })
+        );
+        Ok(())
+    }
+}
@@ -1,6 +1,13 @@
-use std::collections::HashSet;
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
 
-use rand;
+use rand::{self, Rng, SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
 
 pub trait RandomProvider {
     /// Returns a random value between lower and upper bound, inclusive.
@@ -11,6 +18,10 @@ pub trait RandomProvider {
     fn pick_linear_i64(&self, lower_bound: i64, upper_bound: i64) -> i64;
     /// Returns a random value between lower and upper bound, inclusive.
     fn pick_linear_usize(&self, lower_bound: usize, upper_bound: usize) -> usize;
+    /// The seed the values come from, if `SeededRandomProvider::resume` can continue them
+    fn resumable_seed(&self) -> Option<u64> {
+        None
+    }
 }
 
 pub trait RandomPicker<T> {
@@ -108,11 +119,297 @@ impl RandomProvider for DefaultRandomProvider {
     }
 }
 
+/// Deterministic provider that produces the same sequence of values for the same seed.
+pub struct SeededRandomProvider {
+    seed: u64,
+    rng: Mutex<StdRng>,
+}
+
+impl SeededRandomProvider {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    /// Creates a provider with a randomly chosen seed, which can be retrieved with `seed()`.
+    pub fn from_random_seed() -> Self {
+        Self::new(rand::random())
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Continues the values of a provider with the same seed after it produced `picks` of them
+    pub fn resume(seed: u64, picks: usize) -> Self {
+        let provider = Self::new(seed);
+        {
+            let mut rng = provider.rng.lock().unwrap();
+            for _ in 0..picks {
+                rng.next_u64();
+            }
+        }
+        provider
+    }
+
+    /// Returns a value between 0 and `max`, inclusive. Every value takes exactly one word from the
+    /// generator, unlike `random_range`, so where the generator is at only depends on the seed and
+    /// how many values were drawn.
+    fn pick_offset(&self, max: u64) -> u64 {
+        let word = self.rng.lock().unwrap().next_u64();
+        match max.checked_add(1) {
+            Some(count) => ((u128::from(word) * u128::from(count)) >> 64) as u64,
+            None => word,
+        }
+    }
+}
+
+impl RandomProvider for SeededRandomProvider {
+    fn pick_linear_u64(&self, lower_bound: u64, upper_bound: u64) -> u64 {
+        lower_bound + self.pick_offset(upper_bound - lower_bound)
+    }
+
+    fn pick_linear_u32(&self, lower_bound: u32, upper_bound: u32) -> u32 {
+        lower_bound + self.pick_offset((upper_bound - lower_bound).into()) as u32
+    }
+
+    fn pick_linear_i64(&self, lower_bound: i64, upper_bound: i64) -> i64 {
+        lower_bound.wrapping_add(self.pick_offset(upper_bound.abs_diff(lower_bound)) as i64)
+    }
+
+    fn pick_linear_usize(&self, lower_bound: usize, upper_bound: usize) -> usize {
+        lower_bound + self.pick_offset((upper_bound - lower_bound) as u64) as usize
+    }
+
+    fn resumable_seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+}
+
+/// A single call made to a `RandomProvider`, including the bounds requested and the value returned.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum RandomPick {
+    U64 {
+        lower_bound: u64,
+        upper_bound: u64,
+        value: u64,
+    },
+    U32 {
+        lower_bound: u32,
+        upper_bound: u32,
+        value: u32,
+    },
+    I64 {
+        lower_bound: i64,
+        upper_bound: i64,
+        value: i64,
+    },
+    Usize {
+        lower_bound: usize,
+        upper_bound: usize,
+        value: usize,
+    },
+}
+
+pub type RandomPickLog = Arc<Mutex<Vec<RandomPick>>>;
+
+/// Wraps another provider and records every value it produces so it can be replayed later with
+/// `ReplayRandomProvider`.
+pub struct RecordingRandomProvider {
+    inner: Box<dyn RandomProvider>,
+    log: RandomPickLog,
+}
+
+impl RecordingRandomProvider {
+    pub fn new(inner: Box<dyn RandomProvider>) -> Self {
+        Self {
+            inner,
+            log: RandomPickLog::default(),
+        }
+    }
+
+    /// Shared handle to the recorded picks. Remains valid after the provider is moved into a battle.
+    pub fn log(&self) -> RandomPickLog {
+        self.log.clone()
+    }
+
+    fn record(&self, pick: RandomPick) {
+        self.log.lock().unwrap().push(pick);
+    }
+}
+
+impl RandomProvider for RecordingRandomProvider {
+    fn pick_linear_u64(&self, lower_bound: u64, upper_bound: u64) -> u64 {
+        let value = self.inner.pick_linear_u64(lower_bound, upper_bound);
+        self.record(RandomPick::U64 {
+            lower_bound,
+            upper_bound,
+            value,
+        });
+        value
+    }
+
+    fn pick_linear_u32(&self, lower_bound: u32, upper_bound: u32) -> u32 {
+        let value = self.inner.pick_linear_u32(lower_bound, upper_bound);
+        self.record(RandomPick::U32 {
+            lower_bound,
+            upper_bound,
+            value,
+        });
+        value
+    }
+
+    fn pick_linear_i64(&self, lower_bound: i64, upper_bound: i64) -> i64 {
+        let value = self.inner.pick_linear_i64(lower_bound, upper_bound);
+        self.record(RandomPick::I64 {
+            lower_bound,
+            upper_bound,
+            value,
+        });
+        value
+    }
+
+    fn pick_linear_usize(&self, lower_bound: usize, upper_bound: usize) -> usize {
+        let value = self.inner.pick_linear_usize(lower_bound, upper_bound);
+        self.record(RandomPick::Usize {
+            lower_bound,
+            upper_bound,
+            value,
+        });
+        value
+    }
+
+    fn resumable_seed(&self) -> Option<u64> {
+        self.inner.resumable_seed()
+    }
+}
+
+pub type RandomPickCount = Arc<AtomicUsize>;
+
+/// Wraps another provider and counts the values it produces, e.g. to tell whether an action
+/// revealed anything random.
+pub struct CountingRandomProvider {
+    inner: Box<dyn RandomProvider>,
+    count: RandomPickCount,
+}
+
+impl CountingRandomProvider {
+    pub fn new(inner: Box<dyn RandomProvider>) -> Self {
+        Self {
+            inner,
+            count: RandomPickCount::default(),
+        }
+    }
+
+    /// Shared handle to the number of picks. Remains valid after the provider is moved into a
+    /// battle.
+    pub fn count(&self) -> RandomPickCount {
+        self.count.clone()
+    }
+
+    fn counted<T>(&self, value: T) -> T {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+}
+
+impl RandomProvider for CountingRandomProvider {
+    fn pick_linear_u64(&self, lower_bound: u64, upper_bound: u64) -> u64 {
+        self.counted(self.inner.pick_linear_u64(lower_bound, upper_bound))
+    }
+
+    fn pick_linear_u32(&self, lower_bound: u32, upper_bound: u32) -> u32 {
+        self.counted(self.inner.pick_linear_u32(lower_bound, upper_bound))
+    }
+
+    fn pick_linear_i64(&self, lower_bound: i64, upper_bound: i64) -> i64 {
+        self.counted(self.inner.pick_linear_i64(lower_bound, upper_bound))
+    }
+
+    fn pick_linear_usize(&self, lower_bound: usize, upper_bound: usize) -> usize {
+        self.counted(self.inner.pick_linear_usize(lower_bound, upper_bound))
+    }
+
+    fn resumable_seed(&self) -> Option<u64> {
+        self.inner.resumable_seed()
+    }
+}
+
+/// Feeds back picks previously captured by `RecordingRandomProvider`. Panics if the calls made
+/// diverge from the recording, as the replay can no longer be trusted at that point.
+pub struct ReplayRandomProvider {
+    picks: Mutex<VecDeque<RandomPick>>,
+}
+
+impl ReplayRandomProvider {
+    pub fn new(picks: Vec<RandomPick>) -> Self {
+        Self {
+            picks: Mutex::new(picks.into()),
+        }
+    }
+
+    /// Number of recorded picks that have not been consumed yet
+    pub fn remaining(&self) -> usize {
+        self.picks.lock().unwrap().len()
+    }
+
+    fn next_pick(&self) -> RandomPick {
+        self.picks
+            .lock()
+            .unwrap()
+            .pop_front()
+            .expect("Replay ran out of recorded random picks")
+    }
+}
+
+macro_rules! replay_pick {
+    ($self:expr, $variant:ident, $lower_bound:expr, $upper_bound:expr) => {
+        match $self.next_pick() {
+            RandomPick::$variant {
+                lower_bound,
+                upper_bound,
+                value,
+            } if lower_bound == $lower_bound && upper_bound == $upper_bound => value,
+            pick => panic!(
+                "Replay diverged. Expected {} pick between {} and {} but found {:?}",
+                stringify!($variant),
+                $lower_bound,
+                $upper_bound,
+                pick
+            ),
+        }
+    };
+}
+
+impl RandomProvider for ReplayRandomProvider {
+    fn pick_linear_u64(&self, lower_bound: u64, upper_bound: u64) -> u64 {
+        replay_pick!(self, U64, lower_bound, upper_bound)
+    }
+
+    fn pick_linear_u32(&self, lower_bound: u32, upper_bound: u32) -> u32 {
+        replay_pick!(self, U32, lower_bound, upper_bound)
+    }
+
+    fn pick_linear_i64(&self, lower_bound: i64, upper_bound: i64) -> i64 {
+        replay_pick!(self, I64, lower_bound, upper_bound)
+    }
+
+    fn pick_linear_usize(&self, lower_bound: usize, upper_bound: usize) -> usize {
+        replay_pick!(self, Usize, lower_bound, upper_bound)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
 
-    use crate::{DefaultRandomProvider, RandomPicker, RandomProvider};
+    use crate::{
+        DefaultRandomProvider, RandomPick, RandomPicker, RandomProvider, RecordingRandomProvider,
+        ReplayRandomProvider, SeededRandomProvider,
+    };
 
     #[test]
     fn test_pick_linear() {
@@ -177,4 +474,47 @@ mod tests {
         let new_values = values.shuffle(&random);
         assert_eq!(new_values.len(), values.len());
     }
+
+    #[test]
+    fn test_seeded_is_deterministic() {
+        let a = SeededRandomProvider::new(1234);
+        let b = SeededRandomProvider::new(1234);
+        assert_eq!(a.seed(), 1234);
+
+        for _ in 0..100 {
+            assert_eq!(a.pick_linear_u64(0, 1000), b.pick_linear_u64(0, 1000));
+            assert_eq!(a.pick_linear_u32(5, 10), b.pick_linear_u32(5, 10));
+            assert_eq!(a.pick_linear_i64(-10, 10), b.pick_linear_i64(-10, 10));
+            assert_eq!(a.pick_linear_usize(0, 3), b.pick_linear_usize(0, 3));
+        }
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let recorder = RecordingRandomProvider::new(Box::new(SeededRandomProvider::new(99)));
+        let log = recorder.log();
+
+        let values: Vec<u8> = (1..=10).collect();
+        let recorded: Vec<u8> = values.shuffle(&recorder).into_iter().copied().collect();
+        let recorded_i64 = recorder.pick_linear_i64(-5, 5);
+        let recorded_u32 = recorder.pick_linear_u32(0, u32::MAX);
+
+        let replay = ReplayRandomProvider::new(log.lock().unwrap().clone());
+        let replayed: Vec<u8> = values.shuffle(&replay).into_iter().copied().collect();
+        assert_eq!(recorded, replayed);
+        assert_eq!(replay.pick_linear_i64(-5, 5), recorded_i64);
+        assert_eq!(replay.pick_linear_u32(0, u32::MAX), recorded_u32);
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Replay diverged")]
+    fn test_replay_divergence() {
+        let replay = ReplayRandomProvider::new(vec![RandomPick::U64 {
+            lower_bound: 0,
+            upper_bound: 10,
+            value: 5,
+        }]);
+        replay.pick_linear_u64(0, 20);
+    }
 }
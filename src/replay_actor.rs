@@ -0,0 +1,48 @@
+use std::{collections::VecDeque, process::ExitCode, sync::Mutex};
+
+use crate::*;
+use async_trait::async_trait;
+
+/// Replays a fixed sequence of actions previously recorded for a character
+pub struct ReplayActor {
+    character_id: CharacterId,
+    actions: Mutex<VecDeque<Action>>,
+}
+
+impl ReplayActor {
+    pub fn new(character_id: CharacterId, actions: Vec<Action>) -> Self {
+        Self {
+            character_id,
+            actions: Mutex::new(actions.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl Actor for ReplayActor {
+    fn get_character_id(&self) -> &CharacterId {
+        &self.character_id
+    }
+
+    async fn act(&self, _battle: &Battle) -> ActionResult {
+        // Running out exits the battle, which the replay reports along with how far it got
+        self.actions
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or(ActionError::Exit(ExitCode::FAILURE))
+    }
+
+    async fn choose_cards(&self, _battle: &Battle, choice: &CardChoice) -> Vec<CardInstance> {
+        let mut actions = self.actions.lock().unwrap();
+        if let Some(Action::Choose(picked)) = actions.front() {
+            let picked = picked.clone();
+            actions.pop_front();
+            picked
+        } else {
+            choice.default_pick()
+        }
+    }
+
+    async fn on_game_over(&self, _battle: &Battle) {}
+}
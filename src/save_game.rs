@@ -0,0 +1,200 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ActionLogEntry, Battle, BattleEvent, BattleText, Board, Character, CharacterId,
+    CountingRandomProvider, EndConditionType, SeededRandomProvider, map_serde_error,
+};
+
+/// Incremented whenever the save format changes in a way older saves cannot be read
+pub const SAVE_GAME_VERSION: u32 = 3;
+
+#[derive(Deserialize)]
+struct SaveGameVersion {
+    version: u32,
+}
+
+/// Where the random values of a seeded battle were at, so a resumed battle rolls the same ones
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RandomState {
+    pub seed: u64,
+    /// Number of values drawn so far
+    pub picks: usize,
+}
+
+/// Runtime state of a battle. Static definitions such as cards, effects and teams are not stored
+/// and instead come from the battle file the save is loaded into.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SaveGame {
+    pub version: u32,
+    pub round: u16,
+    pub characters: HashMap<CharacterId, Character>,
+    pub board: Board,
+    pub history: Vec<BattleText>,
+    #[serde(default)]
+    pub events: Vec<BattleEvent>,
+    pub action_log: Vec<ActionLogEntry>,
+    pub end_state: Option<EndConditionType>,
+    /// Not set for battles without a seed
+    #[serde(default)]
+    pub random: Option<RandomState>,
+}
+
+impl SaveGame {
+    pub fn parse_from_str(data: &str) -> Result<Self, String> {
+        let SaveGameVersion { version } =
+            serde_json::from_str(data).map_err(|err| map_serde_error(data, err))?;
+        if version != SAVE_GAME_VERSION {
+            return Err(format!(
+                "Unsupported save version {version}. Expected {SAVE_GAME_VERSION}"
+            ));
+        }
+
+        serde_json::from_str(data).map_err(|err| map_serde_error(data, err))
+    }
+}
+
+impl Battle {
+    /// Serializes the runtime state of the battle. Saves are expected to be made between rounds.
+    pub fn save(&self) -> Result<String, String> {
+        serde_json::to_string(&SaveGame {
+            version: SAVE_GAME_VERSION,
+            round: self.round,
+            characters: self.characters.clone(),
+            board: self.board.clone(),
+            history: self.history.clone(),
+            events: self.events.clone(),
+            action_log: self.action_log.clone(),
+            end_state: self.end_state,
+            random: self
+                .random_provider
+                .resumable_seed()
+                .map(|seed| RandomState {
+                    seed,
+                    picks: self.random_picks.load(Ordering::Relaxed),
+                }),
+        })
+        .map_err(|err| err.to_string())
+    }
+
+    /// Restores state written by `save` onto a battle freshly deserialized from the same battle
+    /// file. The actors of this battle are kept, and the next call to `advance` starts the round
+    /// after the one the save was made in. A seeded battle goes on to roll the values the saved one
+    /// would have.
+    pub fn load(&mut self, save_data: &str) -> Result<(), String> {
+        let save = SaveGame::parse_from_str(save_data)?;
+
+        if save.characters.keys().collect::<HashSet<_>>()
+            != self.characters.keys().collect::<HashSet<_>>()
+        {
+            return Err("Save contains different characters than the battle".to_string());
+        }
+        if save.board.grid.width() != self.board.grid.width()
+            || save.board.grid.height() != self.board.grid.height()
+        {
+            return Err("Save has a different board size than the battle".to_string());
+        }
+        for character in save.characters.values() {
+            if let Some(card_instance) = character
+                .hand
+                .iter()
+                .chain(&character.deck)
+                .chain(&character.discard)
+                .find(|card_instance| !self.cards.contains_key(&card_instance.card_id))
+            {
+                return Err(format!(
+                    "Save references non-existent card id {}",
+                    card_instance.card_id
+                ));
+            }
+            if let Some(effect_id) = character
+                .effects
+                .iter()
+                .map(|instance| instance.effect_id)
+                .find(|effect_id| !self.effects.contains_key(effect_id))
+            {
+                return Err(format!(
+                    "Save references non-existent effect id {effect_id}"
+                ));
+            }
+        }
+
+        self.round = save.round;
+        self.characters = save.characters;
+        self.board = save.board;
+        self.history = save.history;
+        self.events = save.events;
+        self.action_log = save.action_log;
+        self.end_state = save.end_state;
+        if let Some(RandomState { seed, picks }) = save.random {
+            let random_provider =
+                CountingRandomProvider::new(Box::new(SeededRandomProvider::resume(seed, picks)));
+            self.random_picks = random_provider.count();
+            self.random_picks.store(picks, Ordering::Relaxed);
+            self.random_provider = Box::new(random_provider);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Battle, DumbActor, SeededRandomProvider};
+
+    const BATTLE: &str = include_str!("../data/sample-battle.json");
+
+    fn new_battle(seed: u64) -> Result<Battle, String> {
+        Battle::deserialize_with_actors(
+            BATTLE,
+            None,
+            Box::new(SeededRandomProvider::new(seed)),
+            |id, _| Box::new(DumbActor { character_id: id }),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_save_round_trip() -> Result<(), String> {
+        let mut battle = new_battle(1)?;
+        battle.advance().await.unwrap();
+        battle.advance().await.unwrap();
+        assert!(battle.end_state.is_none());
+        let save = battle.save()?;
+
+        let mut loaded = new_battle(2)?;
+        loaded.load(&save)?;
+        assert_eq!(loaded.round, 2);
+        assert_eq!(loaded.history.len(), battle.history.len());
+        assert_eq!(loaded.action_log, battle.action_log);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&loaded.save()?).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&save).unwrap()
+        );
+
+        // Resuming continues the saved random values rather than those of the loading battle
+        loaded.run_to_completion().await.unwrap();
+        assert!(loaded.end_state.is_some());
+        assert!(loaded.round > 2);
+        battle.run_to_completion().await.unwrap();
+        assert_eq!(loaded.events, battle.events);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_rejects_other_versions() -> Result<(), String> {
+        let battle = new_battle(1)?;
+        let save = battle
+            .save()?
+            .replacen("\"version\":3", "\"version\":999", 1);
+
+        let mut loaded = new_battle(1)?;
+        assert_eq!(
+            loaded.load(&save),
+            Err("Unsupported save version 999. Expected 3".to_string())
+        );
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use std::marker::PhantomData;
 
 use schemars::JsonSchema;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 pub trait TemplateRenderer<TypeId> {
     fn render_text(&self, string: &str) -> String {
@@ -11,7 +11,7 @@ pub trait TemplateRenderer<TypeId> {
     fn render(&self, type_id: &TypeId, string: &str) -> String;
 }
 
-#[derive(Serialize, JsonSchema)]
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub enum TemplateEntry<TypeId> {
     Text(String),
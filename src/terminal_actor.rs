@@ -1,19 +1,28 @@
-use std::rc::Rc;
+use std::{rc::Rc, sync::Mutex};
 
 use crate::*;
 use async_trait::async_trait;
 
 pub struct TerminalActor {
     pub character_id: CharacterId,
+    /// Why the previous action was refused, shown on the next prompt
+    rejection: Mutex<Option<String>>,
 }
 
 impl TerminalActor {
-    fn get_valid_target(
+    pub fn new(character_id: CharacterId) -> Self {
+        Self {
+            character_id,
+            rejection: Mutex::new(None),
+        }
+    }
+
+    fn select<T>(
         &self,
         blocks: &mut Vec<TerminalBlock>,
-        mut menu: BattleMenu,
+        mut menu: Menu<Battle, T>,
         battle: &Battle,
-    ) -> Result<Option<(CharacterId, CardInstance)>, ActionError> {
+    ) -> Result<T, ActionError> {
         blocks.push(TerminalBlock::default());
         blocks.push(TerminalBlock {
             prefix: TerminalSpan {
@@ -24,11 +33,20 @@ impl TerminalActor {
             ..Default::default()
         });
 
-        let action = menu.wait_for_selection(blocks, battle)?;
-        match action {
-            BattleMenuOutput::Pass => Ok(None),
-            BattleMenuOutput::Card { target, card } => Ok(Some((target, card))),
-        }
+        menu.wait_for_selection(blocks, battle)
+    }
+
+    fn select_action(
+        &self,
+        blocks: &mut Vec<TerminalBlock>,
+        menu: BattleMenu,
+        battle: &Battle,
+    ) -> ActionResult {
+        Ok(match self.select(blocks, menu, battle)? {
+            BattleMenuOutput::Pass => Action::Pass,
+            BattleMenuOutput::Undo => Action::Undo,
+            BattleMenuOutput::Card { target, card } => Action::Act(card, target),
+        })
     }
 
     fn get_battle_status(&self, battle: &Battle) -> Vec<TerminalBlock> {
@@ -42,7 +60,7 @@ impl TerminalActor {
             blocks.push(TerminalBlock::default());
         }
 
-        for team in &battle.teams {
+        for team in battle.teams.iter() {
             blocks.push(TerminalBlock::new(format!("Team: {}", team.name)));
 
             for (team_id, actor) in &battle.actors {
@@ -66,7 +84,7 @@ impl TerminalActor {
     fn get_enemies(&self, battle: &Battle) -> Vec<CharacterId> {
         let mut enemies = vec![];
         let my_team = battle.get_team_for_actor(self);
-        for team in &battle.teams {
+        for team in battle.teams.iter() {
             for (team_id, actor) in &battle.actors {
                 if team_id != &team.id {
                     continue;
@@ -118,27 +136,84 @@ impl Actor for TerminalActor {
     async fn act(&self, battle: &Battle) -> ActionResult {
         let mut blocks = self.get_battle_status(battle);
         blocks.push(TerminalBlock::default());
+        if let Some(rejection) = self.rejection.lock().unwrap().take() {
+            blocks.push(TerminalBlock {
+                prefix: TerminalSpan {
+                    contents: "! ".into(),
+                    color: Some(Box::new(termion::color::Red)),
+                },
+                contents: rejection,
+                ..Default::default()
+            });
+            blocks.push(TerminalBlock::default());
+        }
 
-        let menu = BattleMenu::new(vec![
+        let mut items: Vec<Rc<dyn MenuItem<Battle, BattleMenuOutput>>> = vec![
             Rc::new(ActionsMenu {
                 me: self.character_id,
-                cards: battle.get_character(self).hand.clone(),
-                targets: battle
-                    .characters
-                    .iter()
-                    .filter_map(
-                        |(id, character)| if character.is_dead() { None } else { Some(*id) },
-                    )
-                    .collect(),
+                legal_actions: battle.legal_actions(self.character_id),
             }),
             Rc::new(PassMenuItem {}),
-        ]);
+        ];
+        if battle.undo_history.check().is_ok() {
+            items.push(Rc::new(UndoMenuItem {}));
+        }
 
-        if let Some((target_id, card_id)) = self.get_valid_target(&mut blocks, menu, battle)? {
-            Ok(Action::Act(card_id, target_id))
-        } else {
-            Ok(Action::Pass)
+        self.select_action(&mut blocks, BattleMenu::new(items), battle)
+    }
+
+    async fn choose_cards(&self, battle: &Battle, choice: &CardChoice) -> Vec<CardInstance> {
+        // Cards are picked one at a time, in order for a reorder
+        let mut picked = vec![];
+        while picked.len() < choice.count {
+            let left: Vec<CardInstance> = choice
+                .cards
+                .iter()
+                .filter(|card| !picked.contains(*card))
+                .copied()
+                .collect();
+            if left.len() == 1 {
+                picked.extend(left);
+                break;
+            }
+
+            let mut blocks = self.get_battle_status(battle);
+            blocks.push(TerminalBlock::default());
+            let more = choice.count - picked.len();
+            blocks.push(TerminalBlock::new(match choice.kind {
+                CardChoiceKind::Discard => format!("Choose {more} more card(s) to discard"),
+                CardChoiceKind::Exhaust => format!("Choose {more} more card(s) to exhaust"),
+                CardChoiceKind::Reorder if picked.is_empty() => {
+                    "Choose the card to put on top of your deck".to_string()
+                }
+                CardChoiceKind::Reorder => "Choose the card to put under it".to_string(),
+            }));
+
+            let items = left
+                .into_iter()
+                .map(|card| -> Rc<dyn MenuItem<Battle, CardInstance>> {
+                    Rc::new(CardPickItem { card })
+                })
+                .collect();
+            match self.select(&mut blocks, Menu::new(items), battle) {
+                Ok(card) => picked.push(card),
+                // The battle picks the default for choices left unfinished
+                Err(_) => break,
+            }
         }
+        picked
+    }
+
+    async fn on_action_rejected(
+        &self,
+        _battle: &Battle,
+        _action: &Action,
+        rejection: &ActionRejection,
+    ) {
+        self.rejection
+            .lock()
+            .unwrap()
+            .replace(rejection.to_string());
     }
 
     async fn on_game_over(&self, battle: &Battle) {
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use crate::{ActionRejection, Battle, Board, Character, CharacterId};
+
+/// The parts of a battle an action can change, taken just before the action
+pub struct UndoSnapshot {
+    characters: HashMap<CharacterId, Character>,
+    board: Board,
+}
+
+impl UndoSnapshot {
+    pub fn new(battle: &Battle) -> Self {
+        Self {
+            characters: battle.characters.clone(),
+            board: battle.board.clone(),
+        }
+    }
+
+    /// Puts the battle back as it was. Events are kept, as observers have already seen them, so
+    /// the caller records the undo as an event of its own.
+    pub fn restore(self, battle: &mut Battle) {
+        battle.characters = self.characters;
+        battle.board = self.board;
+    }
+}
+
+/// The actions the acting character may still take back. Only actions from the current turn can be
+/// undone, and none once an action revealed a random value, e.g. how much damage a card dealt.
+#[derive(Default)]
+pub struct UndoHistory {
+    snapshots: Vec<UndoSnapshot>,
+    random_revealed: bool,
+}
+
+impl UndoHistory {
+    /// Forgets every action, e.g. at the start of a turn
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.random_revealed = false;
+    }
+
+    /// Records an accepted action by the state before it
+    pub fn push(&mut self, snapshot: UndoSnapshot, revealed_random: bool) {
+        if revealed_random {
+            self.snapshots.clear();
+            self.random_revealed = true;
+        } else if !self.random_revealed {
+            self.snapshots.push(snapshot);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<UndoSnapshot> {
+        self.snapshots.pop()
+    }
+
+    /// Whether there is an action to undo, and if not why
+    pub fn check(&self) -> Result<(), ActionRejection> {
+        if !self.snapshots.is_empty() {
+            Ok(())
+        } else if self.random_revealed {
+            Err(ActionRejection::RandomRevealed)
+        } else {
+            Err(ActionRejection::NothingToUndo)
+        }
+    }
+}
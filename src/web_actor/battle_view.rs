@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    Battle, BattleEvent, BattleText, Board, BoardItem, Card, CardId, CardInstance, Character,
+    CharacterId, CharacterRace, Content, Effect, EffectId, EffectInstance, EndCondition,
+    EndConditionType, Health, Object, ObjectId, Team,
+    battle_file::{HandSize, StoryCard},
+};
+
+/// What a viewer is allowed to know about a battle. Cards held by a character are only revealed to
+/// that character, and what a character contains only to characters that can reach it. The order
+/// of decks is never revealed, and neither are events telling which cards another character got.
+/// Spectators have no character and so only see public information.
+#[derive(Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(rename = "Battle")]
+pub struct BattleView<'battle> {
+    characters: HashMap<CharacterId, CharacterView<'battle>>,
+    introduction: &'battle Option<StoryCard>,
+    teams: &'battle [Team],
+    /// Rendered from the events the viewer may see
+    history: Vec<BattleText>,
+    events: Vec<&'battle BattleEvent>,
+    round: u16,
+    cards: &'battle HashMap<CardId, Card>,
+    effects: &'battle HashMap<EffectId, Effect>,
+    objects: &'battle HashMap<ObjectId, Object>,
+    default_turn_actions: u64,
+    board: &'battle Board,
+    background_image: &'battle Option<String>,
+    end_conditions: &'battle [EndCondition],
+    end_state: Option<EndConditionType>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(rename = "Character")]
+struct CharacterView<'battle> {
+    id: CharacterId,
+    name: &'battle str,
+    effects: &'battle [EffectInstance],
+    race: &'battle CharacterRace,
+    /// Only present for the viewer's own character
+    hand: Option<&'battle [CardInstance]>,
+    hand_count: usize,
+    deck_count: usize,
+    /// Only present for the viewer's own character
+    discard: Option<&'battle [CardInstance]>,
+    discard_count: usize,
+    health: Health,
+    max_health: Health,
+    remaining_actions: u64,
+    hand_size: HandSize,
+    /// Only present for the viewer's own character and characters within its reach
+    contains: Option<&'battle [Content]>,
+    image: &'battle str,
+    movement: u64,
+    default_movement: u64,
+    armor: u64,
+    block: u64,
+    shield: u64,
+    energy: u64,
+    max_energy: Option<u64>,
+}
+
+impl<'battle> BattleView<'battle> {
+    /// Projects the battle as seen by `viewer`, or by a spectator when there is none
+    pub fn new(battle: &'battle Battle, viewer: Option<CharacterId>) -> Self {
+        let events: Vec<_> = battle
+            .events
+            .iter()
+            .filter(|event| event.is_visible_to(viewer))
+            .collect();
+        Self {
+            characters: battle
+                .characters
+                .iter()
+                .map(|(id, character)| (*id, CharacterView::new(battle, character, viewer)))
+                .collect(),
+            introduction: &battle.introduction,
+            teams: &battle.teams,
+            history: events
+                .iter()
+                .filter_map(|event| event.to_battle_text(battle))
+                .collect(),
+            events,
+            round: battle.round,
+            cards: &battle.cards,
+            effects: &battle.effects,
+            objects: &battle.objects,
+            default_turn_actions: battle.default_turn_actions,
+            board: &battle.board,
+            background_image: &battle.background_image,
+            end_conditions: &battle.end_conditions,
+            end_state: battle.end_state,
+        }
+    }
+}
+
+impl<'battle> CharacterView<'battle> {
+    fn new(battle: &Battle, character: &'battle Character, viewer: Option<CharacterId>) -> Self {
+        let is_viewer = viewer == Some(character.id);
+        let within_reach = viewer.is_some_and(|viewer| {
+            battle
+                .board
+                .distance(
+                    BoardItem::Character(viewer),
+                    BoardItem::Character(character.id),
+                )
+                .is_some_and(|distance| {
+                    distance <= battle.characters[&viewer].reach_distance() as u64
+                })
+        });
+
+        Self {
+            id: character.id,
+            name: &character.name,
+            effects: &character.effects,
+            race: &character.race,
+            hand: is_viewer.then_some(character.hand.as_slice()),
+            hand_count: character.hand.len(),
+            deck_count: character.deck.len(),
+            discard: is_viewer.then_some(character.discard.as_slice()),
+            discard_count: character.discard.len(),
+            health: character.health,
+            max_health: character.max_health,
+            remaining_actions: character.remaining_actions,
+            hand_size: character.hand_size,
+            contains: (is_viewer || within_reach).then_some(character.contains.as_slice()),
+            image: &character.image,
+            movement: character.movement,
+            default_movement: character.default_movement,
+            armor: character.armor,
+            block: character.block,
+            shield: character.shield,
+            energy: character.energy,
+            max_energy: character.max_energy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Battle, BattleEvent, CharacterId, Content, DumbActor, Pile, SeededRandomProvider};
+
+    use super::BattleView;
+
+    #[test]
+    fn test_only_reveals_own_cards() -> Result<(), String> {
+        let battle = Battle::deserialize_with_actors(
+            include_str!("../../data/sample-battle.json"),
+            None,
+            Box::new(SeededRandomProvider::new(1)),
+            |id, _| Box::new(DumbActor { character_id: id }),
+        )?;
+        let viewer = CharacterId::new(0);
+
+        let view = serde_json::to_value(BattleView::new(&battle, Some(viewer))).unwrap();
+        for (id, character) in view["characters"].as_object().unwrap() {
+            let expected = &battle.characters[&CharacterId::new(id.parse().unwrap())];
+            assert_eq!(character["hand_count"], expected.hand.len());
+            assert!(character.get("deck").is_none());
+            if *id == viewer.to_string() {
+                assert_eq!(
+                    character["hand"].as_array().unwrap().len(),
+                    expected.hand.len()
+                );
+                assert!(character["discard"].is_array());
+            } else {
+                assert!(character["hand"].is_null());
+                assert!(character["discard"].is_null());
+            }
+        }
+
+        let spectator = serde_json::to_value(BattleView::new(&battle, None)).unwrap();
+        for character in spectator["characters"].as_object().unwrap().values() {
+            assert!(character["hand"].is_null());
+            assert!(character["contains"].is_null());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_only_reveals_own_card_events() -> Result<(), String> {
+        let mut battle = Battle::deserialize_with_actors(
+            include_str!("../../data/sample-battle.json"),
+            None,
+            Box::new(SeededRandomProvider::new(1)),
+            |id, _| Box::new(DumbActor { character_id: id }),
+        )?;
+        let (viewer, other) = (CharacterId::new(0), CharacterId::new(1));
+        let card = battle.characters[&other].deck[0];
+        let card_name = &battle.cards[&card.card_id].name;
+        battle.events = vec![
+            BattleEvent::CardAdded {
+                character_id: other,
+                card,
+                pile: Pile::Hand,
+            },
+            BattleEvent::ItemTaken {
+                character_id: other,
+                item: Content::Card(card),
+            },
+            BattleEvent::CharacterDied {
+                character_id: other,
+            },
+        ];
+
+        let events = |viewer| {
+            let view = serde_json::to_value(BattleView::new(&battle, viewer)).unwrap();
+            (view["events"].clone(), view["history"].to_string())
+        };
+        let (other_events, other_history) = events(Some(other));
+        assert_eq!(other_events.as_array().unwrap().len(), 3);
+        assert!(other_history.contains(card_name.as_str()));
+        for viewer in [Some(viewer), None] {
+            let (events, history) = events(viewer);
+            assert_eq!(
+                events,
+                serde_json::to_value([BattleEvent::CharacterDied {
+                    character_id: other
+                }])
+                .unwrap()
+            );
+            assert!(!history.contains(card_name.as_str()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use actix_web::Responder;
+use actix_web_lab::sse;
+use futures::stream;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Events a subscriber may fall behind by before it skips ahead
+const CAPACITY: usize = 16;
+
+/// Sends server-sent events to any number of subscribers. Sending never fails: events sent without
+/// subscribers are dropped, and subscribers are removed as soon as they disconnect.
+#[derive(Clone)]
+pub struct EventHub {
+    sender: broadcast::Sender<sse::Event>,
+    /// Sent to subscribers as soon as they connect
+    retained: Arc<Mutex<Option<sse::Event>>>,
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(CAPACITY).0,
+            retained: Default::default(),
+        }
+    }
+}
+
+impl EventHub {
+    pub fn send(&self, event: sse::Event) {
+        // Only fails when there are no subscribers
+        let _ = self.sender.send(event);
+    }
+
+    /// Like `send` but also keeps the event for subscribers that connect later
+    pub fn send_retained(&self, event: sse::Event) {
+        self.retained.lock().unwrap().replace(event.clone());
+        self.send(event);
+    }
+
+    /// Subscribes a new connection, starting with the retained event if there is one
+    pub fn subscribe(&self) -> impl Responder + use<> {
+        let receiver = self.sender.subscribe();
+        let retained = self.retained.lock().unwrap().clone();
+        let events = stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        });
+
+        sse::Sse::from_infallible_stream(stream::StreamExt::chain(stream::iter(retained), events))
+            .with_retry_duration(Duration::from_secs(10))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web_lab::sse;
+
+    use super::EventHub;
+
+    fn event(data: &str) -> sse::Event {
+        sse::Data::new(data.to_string()).into()
+    }
+
+    #[test]
+    fn test_subscribers_come_and_go() {
+        let hub = EventHub::default();
+        hub.send(event("nobody is listening"));
+
+        let first = hub.sender.subscribe();
+        let mut second = hub.sender.subscribe();
+        assert_eq!(hub.sender.receiver_count(), 2);
+        drop(first);
+        assert_eq!(hub.sender.receiver_count(), 1);
+
+        hub.send_retained(event("state"));
+        assert!(second.try_recv().is_ok());
+        assert!(hub.retained.lock().unwrap().is_some());
+    }
+}
@@ -1,27 +1,146 @@
+use super::event_hub::EventHub;
 use crate::{
-    Action, ActionError, ActionFailure, ActionResult, CardId, CardInstance, CardInstanceId,
-    CharacterId, GridLocation, TakeActionItem,
+    Action, ActionRejection, CardId, CardInstance, CardInstanceId, CharacterId, GridLocation,
+    TakeActionItem,
 };
-use actix_web::{HttpResponse, Responder, get, post, web};
-use actix_web_lab::sse;
-use serde::Deserialize;
-use std::{sync::Arc, time::Duration};
-use tokio::sync::{
-    Mutex,
-    mpsc::{Sender, channel, error::SendError},
+use actix_web::{HttpResponse, Responder, ResponseError, get, http::StatusCode, post, web};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{self, Arc},
 };
+use tokio::sync::{Mutex, mpsc::Sender, oneshot};
 
-pub type ArcEventSender = Arc<Mutex<Option<Sender<sse::Event>>>>;
 pub type ArcServerState = Arc<Mutex<ServerState>>;
 
+/// Tells the handler that forwarded an action whether the battle accepted it
+pub type ActionReply = oneshot::Sender<Result<(), RequestError>>;
+
 pub enum BattleServerEvent {
-    Action(ActionResult),
+    /// An action answering the prompt with the given number
+    Action(u64, Action, ActionReply),
     BattleRequest,
 }
 
-pub struct ServerState {
-    pub event_tx: ArcEventSender,
+/// Why a request was refused. Sent to the client as JSON along with a readable message.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestError {
+    /// The request could not be parsed
+    Invalid(String),
+    SeatRequired,
+    UnknownSeat,
+    NotYourTurn,
+    Rejected(ActionRejection),
+    BattleOver,
+}
+
+impl Display for RequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestError::Invalid(message) => write!(f, "Invalid request: {message}"),
+            RequestError::SeatRequired => write!(f, "A seat must be specified"),
+            RequestError::UnknownSeat => write!(f, "Unknown seat"),
+            RequestError::NotYourTurn => write!(f, "It is not your turn"),
+            RequestError::Rejected(rejection) => write!(f, "{rejection}"),
+            RequestError::BattleOver => write!(f, "The battle is over"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a RequestError,
+    message: String,
+}
+
+impl ResponseError for RequestError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            RequestError::Invalid(_) | RequestError::SeatRequired => StatusCode::BAD_REQUEST,
+            RequestError::UnknownSeat => StatusCode::FORBIDDEN,
+            RequestError::NotYourTurn => StatusCode::CONFLICT,
+            RequestError::Rejected(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            RequestError::BattleOver => StatusCode::GONE,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ErrorBody {
+            error: self,
+            message: self.to_string(),
+        })
+    }
+}
+
+/// Sent back once the battle has accepted an action
+#[derive(Serialize)]
+struct ActionApplied {
+    action: Action,
+}
+
+/// A player's connection to the battle, identified by a session token in the `seat` query parameter
+pub struct Seat {
+    pub events: EventHub,
     pub action_tx: Sender<BattleServerEvent>,
+    /// Set while the battle is waiting on this seat's character to act, to the number of the prompt
+    /// it is waiting on. Each action and card choice the character is asked for is a new prompt.
+    pub acting: Arc<sync::Mutex<Option<u64>>>,
+}
+
+#[derive(Default)]
+pub struct ServerState {
+    pub seats: HashMap<String, Seat>,
+    /// Read-only connections that are sent the state of the battle after every change
+    pub spectators: EventHub,
+}
+
+impl ServerState {
+    /// Finds the seat a request is for. The token may be left out when there is only one seat.
+    fn seat(&self, query: &SeatQuery) -> Result<&Seat, RequestError> {
+        match &query.seat {
+            Some(token) => self.seats.get(token).ok_or(RequestError::UnknownSeat),
+            None if self.seats.len() == 1 => Ok(self.seats.values().next().unwrap()),
+            None => Err(RequestError::SeatRequired),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SeatQuery {
+    seat: Option<String>,
+}
+
+/// Forwards an action to the seat's actor and responds once the battle has accepted or refused it
+async fn send_action(
+    query: &SeatQuery,
+    state: &ArcServerState,
+    action: Action,
+) -> Result<HttpResponse, RequestError> {
+    let (action_tx, prompt) = {
+        let state = state.lock().await;
+        let seat = state.seat(query)?;
+        let Some(prompt) = *seat.acting.lock().unwrap() else {
+            return Err(RequestError::NotYourTurn);
+        };
+        (seat.action_tx.clone(), prompt)
+    };
+
+    // The prompt may be over by the time the action arrives, in which case the actor refuses it
+    let (reply_tx, reply_rx) = oneshot::channel();
+    action_tx
+        .send(BattleServerEvent::Action(prompt, action.clone(), reply_tx))
+        .await
+        .map_err(|_| RequestError::BattleOver)?;
+    reply_rx.await.map_err(|_| RequestError::BattleOver)??;
+    Ok(HttpResponse::Ok().json(ActionApplied { action }))
+}
+
+/// Reports malformed JSON bodies and query strings in the same form as other refused requests
+pub fn invalid_request(error: impl Display) -> actix_web::Error {
+    RequestError::Invalid(error.to_string()).into()
 }
 
 #[derive(Deserialize)]
@@ -42,23 +161,22 @@ struct ActParams {
 
 #[post("/act")]
 async fn handle_act(
+    query: web::Query<SeatQuery>,
     info: web::Json<ActParams>,
     state: web::Data<ArcServerState>,
 ) -> impl Responder {
-    state
-        .lock()
-        .await
-        .action_tx
-        .send(BattleServerEvent::Action(ActionResult::Ok(Action::Act(
+    send_action(
+        &query,
+        &state,
+        Action::Act(
             CardInstance {
                 card_id: CardId::new(info.card_id),
                 card_instance_id: CardInstanceId::new(info.card_instance_id),
             },
             CharacterId::new(info.target_id),
-        ))))
-        .await
-        .unwrap();
-    HttpResponse::Ok()
+        ),
+    )
+    .await
 }
 
 #[derive(Deserialize)]
@@ -84,14 +202,14 @@ struct TakeParams {
 
 #[post("/take")]
 async fn handle_take(
+    query: web::Query<SeatQuery>,
     info: web::Json<TakeParams>,
     state: web::Data<ArcServerState>,
 ) -> impl Responder {
-    state
-        .lock()
-        .await
-        .action_tx
-        .send(BattleServerEvent::Action(ActionResult::Ok(Action::Take(
+    send_action(
+        &query,
+        &state,
+        Action::Take(
             CharacterId::new(info.target_id),
             GridLocation {
                 x: info.from.x,
@@ -107,10 +225,9 @@ async fn handle_take(
                     object_instance_id,
                 } => TakeActionItem::Object(object_id, object_instance_id),
             },
-        ))))
-        .await
-        .unwrap();
-    HttpResponse::Ok()
+        ),
+    )
+    .await
 }
 
 #[derive(Deserialize)]
@@ -122,62 +239,103 @@ struct MoveParams {
 
 #[post("/move")]
 async fn handle_move(
+    query: web::Query<SeatQuery>,
     info: web::Json<MoveParams>,
     state: web::Data<ArcServerState>,
 ) -> impl Responder {
-    state
-        .lock()
-        .await
-        .action_tx
-        .send(BattleServerEvent::Action(ActionResult::Ok(Action::Move(
+    send_action(
+        &query,
+        &state,
+        Action::Move(
             CharacterId::new(info.target_id),
             GridLocation {
                 x: info.to.x,
                 y: info.to.y,
             },
-        ))))
-        .await
-        .unwrap();
-    HttpResponse::Ok()
+        ),
+    )
+    .await
 }
 
 #[post("/pass")]
-async fn handle_pass(state: web::Data<ArcServerState>) -> impl Responder {
-    state
-        .lock()
-        .await
-        .action_tx
-        .send(BattleServerEvent::Action(ActionResult::Ok(Action::Pass)))
-        .await
-        .unwrap();
-    HttpResponse::Ok()
+async fn handle_pass(
+    query: web::Query<SeatQuery>,
+    state: web::Data<ArcServerState>,
+) -> impl Responder {
+    send_action(&query, &state, Action::Pass).await
+}
+
+#[post("/undo")]
+async fn handle_undo(
+    query: web::Query<SeatQuery>,
+    state: web::Data<ArcServerState>,
+) -> impl Responder {
+    send_action(&query, &state, Action::Undo).await
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChooseParams {
+    cards: Vec<CardInstance>,
+}
+
+#[post("/choose")]
+async fn handle_choose(
+    query: web::Query<SeatQuery>,
+    info: web::Json<ChooseParams>,
+    state: web::Data<ArcServerState>,
+) -> impl Responder {
+    send_action(&query, &state, Action::Choose(info.into_inner().cards)).await
 }
 
 #[get("/info")]
-async fn handle_info(state: web::Data<ArcServerState>) -> impl Responder {
-    state
-        .lock()
-        .await
-        .action_tx
-        .send(BattleServerEvent::BattleRequest)
-        .await
-        .unwrap();
-    HttpResponse::Ok()
+async fn handle_info(
+    query: web::Query<SeatQuery>,
+    state: web::Data<ArcServerState>,
+) -> Result<HttpResponse, RequestError> {
+    let state = state.lock().await;
+    let seat = state.seat(&query)?;
+    // The state is only available while the seat is acting and is sent at the start of each of
+    // its turns anyway, so a request already waiting is enough
+    let _ = seat.action_tx.try_send(BattleServerEvent::BattleRequest);
+    Ok(HttpResponse::Ok().finish())
 }
 
 #[get("/sse")]
-async fn handle_sse(state: web::Data<ArcServerState>) -> impl Responder {
-    let (tx, rx) = channel(10);
-
-    state.lock().await.event_tx.lock().await.replace(tx);
+async fn handle_sse(
+    query: web::Query<SeatQuery>,
+    state: web::Data<ArcServerState>,
+) -> Result<impl Responder, RequestError> {
+    Ok(state.lock().await.seat(&query)?.events.subscribe())
+}
 
-    sse::Sse::from_infallible_receiver(rx).with_retry_duration(Duration::from_secs(10))
+#[get("/spectate")]
+async fn handle_spectate(state: web::Data<ArcServerState>) -> impl Responder {
+    state.lock().await.spectators.subscribe()
 }
 
-impl From<SendError<sse::Event>> for ActionError {
-    fn from(send_error: SendError<sse::Event>) -> Self {
-        Self::Failure(ActionFailure {
-            message: send_error.to_string(),
-        })
+#[cfg(test)]
+mod tests {
+    use actix_web::{ResponseError, body::to_bytes, http::StatusCode};
+
+    use super::RequestError;
+    use crate::{ActionRejection, CharacterId};
+
+    #[tokio::test]
+    async fn test_request_error_response() {
+        let error = RequestError::Rejected(ActionRejection::TargetDead {
+            target_id: CharacterId::new(2),
+        });
+        let response = error.error_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body: serde_json::Value =
+            serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap();
+        assert_eq!(body["message"], error.to_string());
+        assert!(body["error"]["rejected"].is_object());
+        assert_eq!(
+            RequestError::NotYourTurn.status_code(),
+            StatusCode::CONFLICT
+        );
     }
 }
@@ -1,7 +1,10 @@
+mod battle_view;
+mod event_hub;
 mod handlers;
 mod server;
 #[expect(clippy::module_inception)]
 mod web_actor;
 
+pub use server::ServerConfig;
 pub use web_actor::BattleState; // For codegen
-pub use web_actor::WebActor;
+pub use web_actor::{WebActor, WebServer};
@@ -7,7 +7,7 @@ use actix_web::{
 use futures::executor::block_on;
 use std::{
     marker::PhantomData,
-    path::Path,
+    path::{Path, PathBuf},
     sync::Arc,
     thread::{self, JoinHandle},
 };
@@ -17,12 +17,51 @@ use std::{
 };
 use tokio::sync::Mutex;
 
-use crate::web_actor::handlers::{
-    handle_act, handle_info, handle_move, handle_pass, handle_sse, handle_take,
+use crate::{
+    battle_file::ServerOptions,
+    web_actor::handlers::{
+        handle_act, handle_choose, handle_info, handle_move, handle_pass, handle_spectate,
+        handle_sse, handle_take, handle_undo, invalid_request,
+    },
 };
 
+const STATIC_HOSTING_DIR: &str = concat!(env!("OUT_DIR"), "/static");
+
+pub struct ServerConfig {
+    pub host: String,
+    /// 0 binds to any free port. The port actually bound is available from `Server::url`.
+    pub port: u16,
+    /// Directory to serve the web client from instead of the built one
+    pub static_directory: PathBuf,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 8000,
+            static_directory: PathBuf::from(STATIC_HOSTING_DIR),
+        }
+    }
+}
+
+impl From<ServerOptions> for ServerConfig {
+    fn from(options: ServerOptions) -> Self {
+        let default = Self::default();
+        Self {
+            host: options.host.unwrap_or(default.host),
+            port: options.port.unwrap_or(default.port),
+            static_directory: options
+                .static_directory
+                .map(PathBuf::from)
+                .unwrap_or(default.static_directory),
+        }
+    }
+}
+
 pub struct Server<T> {
     _phantom: PhantomData<T>,
+    url: String,
     server_thread: Option<JoinHandle<std::io::Result<()>>>,
     server_handle: ServerHandle,
 
@@ -40,18 +79,19 @@ async fn server_main<T>(server: actix_web::dev::Server) -> std::io::Result<()> {
 impl<T: Sync + Send + 'static> Server<T> {
     pub fn new(
         server_state: Arc<Mutex<T>>,
+        config: &ServerConfig,
         additional_static_asset_directory: Option<&Path>,
     ) -> Result<Server<T>, std::io::Error>
 where {
-        let host = "0.0.0.0";
-        let port = 8000;
-        const STATIC_HOSTING_DIR: &str = concat!(env!("OUT_DIR"), "/static");
+        let static_directory = config.static_directory.clone();
         let additional_static_asset_directory =
             additional_static_asset_directory.map(Path::to_path_buf);
 
-        let server: actix_web::dev::Server = HttpServer::new(move || {
+        let server = HttpServer::new(move || {
             let mut app = App::new()
                 .app_data(web::Data::new(server_state.clone()))
+                .app_data(web::JsonConfig::default().error_handler(|err, _| invalid_request(err)))
+                .app_data(web::QueryConfig::default().error_handler(|err, _| invalid_request(err)))
                 .wrap(
                     ErrorHandlers::new().default_handler(|service_response: ServiceResponse| {
                         println!(
@@ -68,23 +108,34 @@ where {
                 .service(handle_act)
                 .service(handle_move)
                 .service(handle_pass)
+                .service(handle_undo)
+                .service(handle_choose)
                 .service(handle_info)
                 .service(handle_take)
-                .service(handle_sse);
+                .service(handle_sse)
+                .service(handle_spectate);
             if let Some(dir) = &additional_static_asset_directory {
                 app = app.service(actix_files::Files::new("/ref", dir.clone()).use_etag(true));
             }
             // Must come after the additional directory to ensure resolution
-            app = app.service(actix_files::Files::new("/", STATIC_HOSTING_DIR).use_etag(true));
+            app = app.service(actix_files::Files::new("/", &static_directory).use_etag(true));
             app
         })
         .disable_signals()
-        .bind((host, port))
-        .unwrap()
-        .run();
+        .bind((config.host.as_str(), config.port))?;
+        // Reports the port picked by the OS when binding to port 0
+        let port = server
+            .addrs()
+            .first()
+            .map_or(config.port, |address| address.port());
+        let server = server.run();
         let server_handle = server.handle();
-        println!("Started server on http://{}:{}/index.html", host, port);
-        println!("Serving static assets from {}", STATIC_HOSTING_DIR);
+        let url = format!("http://{}:{}", config.host, port);
+        println!("Started server on {}/index.html", url);
+        println!(
+            "Serving static assets from {}",
+            config.static_directory.display()
+        );
         let server_thread = thread::spawn(|| server_main::<T>(server));
 
         #[cfg(debug_assertions)]
@@ -93,6 +144,7 @@ where {
             let asset_build_thread_terminate_clone = asset_build_thread_terminate.clone();
             Ok(Self {
                 _phantom: PhantomData,
+                url,
                 server_thread: Some(server_thread),
                 server_handle,
                 asset_build_thread: Some(thread::spawn(move || {
@@ -119,6 +171,7 @@ where {
         {
             Ok(Self {
                 _phantom: PhantomData,
+                url,
                 server_thread: Some(server_thread),
                 server_handle,
             })
@@ -126,6 +179,13 @@ where {
     }
 }
 
+impl<T> Server<T> {
+    /// Base URL the server can be reached at
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
 impl<T> Drop for Server<T> {
     fn drop(&mut self) {
         self.asset_build_thread_terminate
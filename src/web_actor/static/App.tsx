@@ -1,16 +1,22 @@
 import * as messages from "./messages.js";
 
-import type { BattleState, CardInstance } from "./battle";
+import type { ActionRejected, BattleState, CardInstance } from "./battle";
 import React, { useEffect, useState } from "react";
-import { getCardTarget, getLivingEnemies } from "./utils.js";
+import {
+  getCardTarget,
+  getLegalTargets,
+  isSpectating,
+  withSeat,
+} from "./utils.js";
 
 import { ActionTarget } from "./battle";
 import BattleHistory from "./BattleHistory.js";
 import Card from "./Card.js";
+import CardChoicePrompt from "./CardChoicePrompt.js";
 import Character from "./Character.js";
 import { GameBoard } from "./GameBoard.js";
 import { StoryCard } from "./StoryCard.js";
-import { takeAction } from "./state.js";
+import { setRequestErrorHandler, takeAction } from "./state.js";
 
 messages.init();
 
@@ -18,10 +24,14 @@ export default function App() {
   const [battleState, setBattleState] = useState<BattleState>();
   const [dragState, setDragState] = useState<CardInstance>();
   const [showIntroState, setShowIntroState] = useState<boolean>(false);
+  const [rejectionState, setRejectionState] = useState<string>();
+  const [turnTimeState, setTurnTimeState] = useState<number>();
 
   useEffect(() => {
     // Throwaway
-    void fetch("/info");
+    if (!isSpectating()) {
+      void fetch(withSeat("/info"));
+    }
 
     const onBattleState = (e: MessageEvent<string>) => {
       const newBattleState = JSON.parse(e.data) as BattleState;
@@ -41,6 +51,48 @@ export default function App() {
     };
   }, [setBattleState]);
 
+  useEffect(() => {
+    let timeout: ReturnType<typeof setTimeout> | undefined;
+    const showRejection = (message: string) => {
+      setRejectionState(message);
+      clearTimeout(timeout);
+      timeout = setTimeout(() => {
+        setRejectionState(undefined);
+      }, 4000);
+    };
+    const onActionRejected = (e: MessageEvent<string>) => {
+      const { message } = JSON.parse(e.data) as ActionRejected;
+      showRejection(message);
+    };
+
+    messages.addEventListener("action_rejected", onActionRejected);
+    setRequestErrorHandler(showRejection);
+    return () => {
+      clearTimeout(timeout);
+      messages.removeEventListener("action_rejected", onActionRejected);
+      setRequestErrorHandler(undefined);
+    };
+  }, [setRejectionState]);
+
+  useEffect(() => {
+    const remaining = battleState?.turn_time_remaining;
+    if (remaining === undefined || remaining === null) {
+      setTurnTimeState(undefined);
+      return;
+    }
+
+    // Count down locally from when the state arrived
+    const deadline = Date.now() + remaining;
+    const tick = () => {
+      setTurnTimeState(Math.max(0, Math.ceil((deadline - Date.now()) / 1000)));
+    };
+    tick();
+    const interval = setInterval(tick, 250);
+    return () => {
+      clearInterval(interval);
+    };
+  }, [battleState]);
+
   useEffect(() => {
     const handleKeyDown = (event: KeyboardEvent) => {
       if (event.key !== "Alt") {
@@ -69,6 +121,8 @@ export default function App() {
   }
 
   const { character_id: characterId, battle } = battleState;
+  // Only the viewer's own hand is sent, and spectators have none
+  const hand = battle.characters[characterId].hand ?? [];
 
   return (
     <div id="app">
@@ -104,6 +158,15 @@ export default function App() {
             ))}
         </div>
 
+        {battleState.choice ? (
+          // Keyed by the events so a new choice starts with nothing picked
+          <CardChoicePrompt
+            battle={battle}
+            choice={battleState.choice}
+            key={battle.events.length}
+          />
+        ) : undefined}
+
         <div
           style={{
             display: "flex",
@@ -111,29 +174,28 @@ export default function App() {
         >
           <GameBoard battleState={battleState} draggedCard={dragState} />
 
-          <ul id="cards">
-            {battle.characters[characterId].hand.map((cardInstance) => {
+          <ul id="cards" hidden={battleState.spectating}>
+            {hand.map((cardInstance) => {
               const card = battle.cards[cardInstance.card_id];
               const target = getCardTarget(card);
+              const legalTargets = getLegalTargets(battleState, cardInstance);
               let defaultAction: undefined | (() => Promise<void>);
-              if (target === ActionTarget.Me) {
+              if (target === ActionTarget.Me && legalTargets.length > 0) {
                 defaultAction = async () =>
                   await takeAction(cardInstance, characterId);
-              } else if (target === ActionTarget.Others) {
-                const enemies = getLivingEnemies(battle, characterId);
-                if (enemies.length === 1) {
-                  defaultAction = async () =>
-                    await takeAction(cardInstance, enemies[0].id);
-                }
+              } else if (
+                target === ActionTarget.Others &&
+                legalTargets.length === 1
+              ) {
+                defaultAction = async () =>
+                  await takeAction(cardInstance, legalTargets[0]);
               }
               return (
                 <li key={cardInstance.card_instance_id}>
                   <Card
                     card={card}
                     cardInstance={cardInstance}
-                    enabled={
-                      battle.characters[characterId].remaining_actions > 0
-                    }
+                    enabled={legalTargets.length > 0}
                     hasDefaultAction={defaultAction !== undefined}
                     onClick={async () => {
                       // Take default actions when clicking buttons
@@ -156,6 +218,10 @@ export default function App() {
       </div>
 
       <div style={{ flexGrow: 2 }}>
+        <div id="turn-timer" hidden={turnTimeState === undefined}>
+          {turnTimeState} seconds left this turn
+        </div>
+        <div id="action-rejection">{rejectionState}</div>
         <BattleHistory history={battle.history} />
       </div>
     </div>
@@ -26,8 +26,8 @@ export default function Card(props: {
   readonly card: Card;
   readonly cardInstance: CardInstance;
   readonly enabled: boolean;
-  readonly onDragStart: () => void;
-  readonly onDragEnd: () => void;
+  readonly onDragStart?: () => void;
+  readonly onDragEnd?: () => void;
   readonly onClick: () => void;
   readonly hasDefaultAction: boolean;
 }) {
@@ -35,7 +35,7 @@ export default function Card(props: {
     <button
       className="card"
       disabled={!props.enabled}
-      draggable={props.enabled}
+      draggable={props.enabled && props.onDragStart !== undefined}
       onClick={props.onClick}
       onDragEnd={props.onDragEnd}
       onDragStart={(e) => {
@@ -43,7 +43,7 @@ export default function Card(props: {
           "text/plain",
           String(props.cardInstance.card_instance_id),
         );
-        props.onDragStart();
+        props.onDragStart?.();
       }}
       style={{
         cursor: props.enabled
@@ -66,6 +66,10 @@ export default function Card(props: {
         {props.card.range > 0 ? (
           <p className="card-range">{props.card.range}</p>
         ) : null}
+
+        {props.card.cost > 0 ? (
+          <p className="card-cost">{props.card.cost}</p>
+        ) : null}
       </div>
     </button>
   );
@@ -0,0 +1,74 @@
+import type { Battle, CardChoice, CardInstance } from "./battle";
+import React, { useState } from "react";
+
+import Card from "./Card.js";
+import { CardChoiceKind } from "./battle";
+import { choose } from "./state.js";
+
+function describe(choice: CardChoice): string {
+  const cards = choice.count === 1 ? "1 card" : `${choice.count} cards`;
+  switch (choice.kind) {
+    case CardChoiceKind.Discard:
+      return `Choose ${cards} to discard`;
+    case CardChoiceKind.Exhaust:
+      return `Choose ${cards} to remove from the game`;
+    case CardChoiceKind.Reorder:
+      return `Put the top ${cards} of your deck back, picking the top one first`;
+  }
+  throw new Error(`Unrecognized CardChoiceKind: ${choice.kind as string}`);
+}
+
+// Asks the player to pick cards before the card they played can carry on
+export default function CardChoicePrompt(props: {
+  readonly battle: Battle;
+  readonly choice: CardChoice;
+}) {
+  const [picked, setPicked] = useState<CardInstance[]>([]);
+  const pickedIndex = (cardInstance: CardInstance) =>
+    picked.findIndex(
+      (other) => other.card_instance_id === cardInstance.card_instance_id,
+    );
+
+  return (
+    <div id="card-choice">
+      <p>{describe(props.choice)}</p>
+      <ul>
+        {props.choice.cards.map((cardInstance) => {
+          const index = pickedIndex(cardInstance);
+          return (
+            <li
+              className={index >= 0 ? "picked" : undefined}
+              key={cardInstance.card_instance_id}
+            >
+              <Card
+                card={props.battle.cards[cardInstance.card_id]}
+                cardInstance={cardInstance}
+                enabled
+                hasDefaultAction
+                onClick={() => {
+                  setPicked(
+                    index >= 0
+                      ? picked.filter((_, other) => other !== index)
+                      : [...picked, cardInstance],
+                  );
+                }}
+              />
+              {props.choice.kind === CardChoiceKind.Reorder && index >= 0 ? (
+                <b>{index + 1}</b>
+              ) : undefined}
+            </li>
+          );
+        })}
+      </ul>
+      <button
+        disabled={picked.length !== props.choice.count}
+        onClick={async () => {
+          await choose(picked);
+        }}
+        type="button"
+      >
+        Confirm
+      </button>
+    </div>
+  );
+}
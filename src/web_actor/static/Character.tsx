@@ -3,10 +3,11 @@ import type {
   BattleState,
   CardInstance,
   CharacterId,
+  EffectInstance,
 } from "./battle";
-import { assetPath, countEntries, cssUrl } from "./utils";
+import { assetPath, cssUrl } from "./utils";
 import { bolt, footsteps } from "./images";
-import { pass, takeAction } from "./state";
+import { pass, takeAction, undo } from "./state";
 import { Character } from "./battle";
 import Container from "./Container";
 import Effect from "./Effect";
@@ -27,9 +28,9 @@ export default function Character(props: {
 
   const [contentsOpened, setContentsOpened] = React.useState(false);
 
-  const effects: [BattleEffect, number][] = Array.from(
-    countEntries(character.effects),
-  ).map(([id, count]) => [battle.effects[id], count]);
+  const effects: [BattleEffect, EffectInstance][] = character.effects.map(
+    (instance) => [battle.effects[instance.effect_id], instance],
+  );
 
   // Only ineligible if there is actively a card being dragged and that card isn't eligible.
   const isIneligible =
@@ -61,7 +62,7 @@ export default function Character(props: {
         <img src={assetPath(character.image)} style={{ width: "100%" }} />
       ) : null}
 
-      {character.contains.length > 0 ? (
+      {character.contains && character.contains.length > 0 ? (
         <button
           className="open"
           onClick={() => {
@@ -107,9 +108,38 @@ export default function Character(props: {
           </span>
         </div>
 
+        <div className="defenses">
+          {character.armor > 0 ? (
+            <span title="Armor: reduces the damage of every hit">
+              🪖 {character.armor}
+            </span>
+          ) : null}
+          {character.block > 0 ? (
+            <span title="Block: absorbs damage until their next turn">
+              🛡️ {character.block}
+            </span>
+          ) : null}
+          {character.shield > 0 ? (
+            <span title="Shield: absorbs damage until used up">
+              🔰 {character.shield}
+            </span>
+          ) : null}
+          {character.energy > 0 || character.max_energy ? (
+            <span title="Energy: spent to play cards">
+              🔋 {character.energy}
+              {character.max_energy ? `/${character.max_energy}` : null}
+            </span>
+          ) : null}
+        </div>
+
         <div className="effects">
-          {effects.map(([effect, count]) => (
-            <Effect count={count} effect={effect} key={effect.id} />
+          {effects.map(([effect, instance]) => (
+            <Effect
+              count={instance.stacks}
+              effect={effect}
+              key={effect.id}
+              remainingTurns={instance.remaining_turns ?? undefined}
+            />
           ))}
         </div>
       </div>
@@ -126,7 +156,19 @@ export default function Character(props: {
         </button>
       ) : null}
 
-      {contentsOpened ? (
+      {isPlayer && battleState.can_undo ? (
+        <button
+          className="character-undo"
+          onClick={async () => {
+            await undo();
+          }}
+          type="button"
+        >
+          Undo
+        </button>
+      ) : null}
+
+      {contentsOpened && character.contains ? (
         <Container
           battleState={battleState}
           characterId={characterId}
@@ -8,11 +8,18 @@ import { assetUrl } from "./utils";
 export default function Effect(props: {
   readonly effect: Effect;
   readonly count: number;
+  readonly remainingTurns?: number;
 }) {
-  const { effect, count } = props;
+  const { effect, count, remainingTurns } = props;
 
   const [effectElement, setEffectElement] = useState<HTMLElement | null>(null);
 
+  const turns = remainingTurns === 1 ? "turn" : "turns";
+  const body =
+    remainingTurns === undefined
+      ? effect.description
+      : `${effect.description} (${remainingTurns} ${turns} left)`;
+
   return (
     <Badge count={count} key={effect.id} showCountBelowTwo={false}>
       <span
@@ -26,7 +33,7 @@ export default function Effect(props: {
         {effectElement ? (
           <HoverTooltip
             anchor={effectElement}
-            body={effect.description}
+            body={body}
             title={effect.name}
           />
         ) : null}
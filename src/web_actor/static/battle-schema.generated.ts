@@ -12,8 +12,45 @@ export interface components {
         /** BattleState */
         "\"BattleState\"": {
             battle: components["schemas"]["Battle"];
+            /** @description Whether the character may take back its last action */
+            can_undo: boolean;
             character_id: components["schemas"]["CharacterId"];
+            /** @description Cards the character has to pick from before anything else happens */
+            choice?: components["schemas"]["CardChoice"] | null;
+            /** @description Everything the character may do right now */
+            legal_actions: components["schemas"]["Action"][];
+            /** @description Set for read-only viewers. Their state follows the acting character but only reveals what
+             *     is public. */
+            spectating: boolean;
+            /**
+             * Format: uint64
+             * @description Milliseconds left before the acting character runs out of time, when turns are limited
+             */
+            turn_time_remaining?: number | null;
         };
+        Action: "Pass" | {
+            Act: [
+                components["schemas"]["CardInstance"],
+                components["schemas"]["CharacterId"]
+            ];
+        } | {
+            Move: [
+                components["schemas"]["CharacterId"],
+                components["schemas"]["GridLocation"]
+            ];
+        } | {
+            Take: [
+                components["schemas"]["CharacterId"],
+                components["schemas"]["GridLocation"],
+                components["schemas"]["TakeActionItem"]
+            ];
+        } | "Undo" | {
+            Choose: components["schemas"]["CardInstance"][];
+        };
+        /** @description What a viewer is allowed to know about a battle. Cards held by a character are only revealed to
+         *     that character, and what a character contains only to characters that can reach it. The order
+         *     of decks is never revealed, and neither are events telling which cards another character got.
+         *     Spectators have no character and so only see public information. */
         Battle: {
             background_image?: string | null;
             board: components["schemas"]["Board"];
@@ -30,6 +67,8 @@ export interface components {
             };
             end_conditions: components["schemas"]["EndCondition"][];
             end_state?: components["schemas"]["EndConditionType"] | null;
+            events: components["schemas"]["BattleEvent"][];
+            /** @description Rendered from the events the viewer may see */
             history: components["schemas"]["TemplateEntry"][][];
             introduction?: components["schemas"]["StoryCardEntry"][] | null;
             objects: {
@@ -39,6 +78,171 @@ export interface components {
             round: number;
             teams: components["schemas"]["Team"][];
         };
+        /** @description Something that happened during a battle. The battle history is rendered from these. */
+        BattleEvent: {
+            RoundStarted: {
+                /** Format: uint16 */
+                round: number;
+            };
+        } | {
+            Passed: {
+                character_id: components["schemas"]["CharacterId"];
+            };
+        } | {
+            CardPlayed: {
+                card: components["schemas"]["CardInstance"];
+                character_id: components["schemas"]["CharacterId"];
+                target_id: components["schemas"]["CharacterId"];
+            };
+        } | {
+            DamageDealt: {
+                /** Format: uint64 */
+                amount: number;
+                /**
+                 * Format: uint64
+                 * @description Damage absorbed by the target's defenses
+                 * @default 0
+                 */
+                blocked?: number;
+                /**
+                 * @description The card whose action dealt the damage. Not set for damage dealt by effects.
+                 * @default null
+                 */
+                card_id?: components["schemas"]["CardId"] | null;
+                /** @default Physical */
+                damage_type?: components["schemas"]["DamageType"];
+                source_id: components["schemas"]["CharacterId"];
+                target_id: components["schemas"]["CharacterId"];
+            };
+        } | {
+            Healed: {
+                /** Format: uint64 */
+                amount: number;
+                source_id: components["schemas"]["CharacterId"];
+                target_id: components["schemas"]["CharacterId"];
+            };
+        } | {
+            ActionsGained: {
+                /** Format: uint64 */
+                amount: number;
+                character_id: components["schemas"]["CharacterId"];
+            };
+        } | {
+            MovementGained: {
+                /** Format: uint64 */
+                amount: number;
+                character_id: components["schemas"]["CharacterId"];
+            };
+        } | {
+            EnergyGained: {
+                /** Format: uint64 */
+                amount: number;
+                character_id: components["schemas"]["CharacterId"];
+            };
+        } | {
+            EnergyLost: {
+                /** Format: uint64 */
+                amount: number;
+                character_id: components["schemas"]["CharacterId"];
+            };
+        } | {
+            CardsDrawn: {
+                character_id: components["schemas"]["CharacterId"];
+                /** Format: uint64 */
+                count: number;
+            };
+        } | {
+            CardsDiscarded: {
+                character_id: components["schemas"]["CharacterId"];
+                /** Format: uint64 */
+                count: number;
+            };
+        } | {
+            CardsExhausted: {
+                character_id: components["schemas"]["CharacterId"];
+                /** Format: uint64 */
+                count: number;
+            };
+        } | {
+            CardAdded: {
+                card: components["schemas"]["CardInstance"];
+                character_id: components["schemas"]["CharacterId"];
+                pile: components["schemas"]["Pile"];
+            };
+        } | {
+            DiscardShuffled: {
+                character_id: components["schemas"]["CharacterId"];
+            };
+        } | {
+            DeckReordered: {
+                character_id: components["schemas"]["CharacterId"];
+                /** Format: uint64 */
+                count: number;
+            };
+        } | {
+            EffectApplied: {
+                character_id: components["schemas"]["CharacterId"];
+                effect_id: components["schemas"]["EffectId"];
+            };
+        } | {
+            EffectRemoved: {
+                character_id: components["schemas"]["CharacterId"];
+                /** Format: uint64 */
+                count: number;
+                effect_id: components["schemas"]["EffectId"];
+            };
+        } | {
+            DefenseGained: {
+                /** Format: uint64 */
+                amount: number;
+                character_id: components["schemas"]["CharacterId"];
+                defense: components["schemas"]["Defense"];
+            };
+        } | {
+            DefenseLost: {
+                /** Format: uint64 */
+                amount: number;
+                character_id: components["schemas"]["CharacterId"];
+                defense: components["schemas"]["Defense"];
+            };
+        } | {
+            EffectExpired: {
+                character_id: components["schemas"]["CharacterId"];
+                effect_id: components["schemas"]["EffectId"];
+            };
+        } | {
+            CardDestroyed: {
+                card: components["schemas"]["CardInstance"];
+                character_id: components["schemas"]["CharacterId"];
+            };
+        } | {
+            Moved: {
+                character_id: components["schemas"]["CharacterId"];
+                from: components["schemas"]["GridLocation"];
+                to: components["schemas"]["GridLocation"];
+            };
+        } | {
+            ItemTaken: {
+                character_id: components["schemas"]["CharacterId"];
+                item: components["schemas"]["Content"];
+            };
+        } | {
+            CharacterDied: {
+                character_id: components["schemas"]["CharacterId"];
+            };
+        } | {
+            TurnTimedOut: {
+                character_id: components["schemas"]["CharacterId"];
+            };
+        } | {
+            ActionUndone: {
+                character_id: components["schemas"]["CharacterId"];
+            };
+        } | {
+            BattleEnded: {
+                end_state: components["schemas"]["EndConditionType"];
+            };
+        };
         /** @enum {string} */
         BattleTextEntry: BattleTextEntry;
         Board: {
@@ -58,6 +262,11 @@ export interface components {
         };
         Card: {
             actions: components["schemas"]["CardAction"][];
+            /**
+             * Format: uint64
+             * @description Energy it takes to play the card, on top of an action
+             */
+            cost: number;
             description: string;
             flavor?: string | null;
             id: components["schemas"]["CardId"];
@@ -69,6 +278,7 @@ export interface components {
             Damage: {
                 amount: components["schemas"]["U64Range"];
                 area: components["schemas"]["U64Range"];
+                damage_type: components["schemas"]["DamageType"];
                 target: components["schemas"]["Target"];
             };
         } | {
@@ -111,7 +321,72 @@ export interface components {
             DestroySelf: {
                 chance: components["schemas"]["Chance"];
             };
+        } | {
+            GainDefense: {
+                amount: components["schemas"]["U64Range"];
+                defense: components["schemas"]["Defense"];
+                target: components["schemas"]["Target"];
+            };
+        } | {
+            StripDefense: {
+                amount?: components["schemas"]["U64Range"] | null;
+                defense: components["schemas"]["Defense"];
+                target: components["schemas"]["Target"];
+            };
+        } | {
+            GainEnergy: {
+                amount: components["schemas"]["U64Range"];
+                target: components["schemas"]["Target"];
+            };
+        } | {
+            LoseEnergy: {
+                amount: components["schemas"]["U64Range"];
+                target: components["schemas"]["Target"];
+            };
+        } | {
+            Draw: {
+                amount: components["schemas"]["U64Range"];
+                target: components["schemas"]["Target"];
+            };
+        } | {
+            Discard: {
+                amount: components["schemas"]["U64Range"];
+                random: boolean;
+                target: components["schemas"]["Target"];
+            };
+        } | {
+            Exhaust: {
+                amount: components["schemas"]["U64Range"];
+                random: boolean;
+                target: components["schemas"]["Target"];
+            };
+        } | {
+            AddCard: {
+                card: components["schemas"]["CardId"];
+                pile: components["schemas"]["Pile"];
+                target: components["schemas"]["Target"];
+            };
+        } | {
+            ShuffleDiscard: {
+                target: components["schemas"]["Target"];
+            };
+        } | {
+            ReorderDeck: {
+                amount: components["schemas"]["U64Range"];
+                target: components["schemas"]["Target"];
+            };
+        };
+        /** @description Cards a character has to pick from before the battle goes on. For a reorder all of them are
+         *     picked, in the order they go back on top of the deck. */
+        CardChoice: {
+            cards: components["schemas"]["CardInstance"][];
+            character_id: components["schemas"]["CharacterId"];
+            /** Format: uint */
+            count: number;
+            kind: components["schemas"]["CardChoiceKind"];
         };
+        /** @enum {string} */
+        CardChoiceKind: CardChoiceKind;
         /** Format: uint */
         CardId: number;
         CardInstance: {
@@ -123,18 +398,34 @@ export interface components {
         /** Format: uint32 */
         Chance: number;
         Character: {
-            contains: components["schemas"]["Content"][];
-            deck: components["schemas"]["CardInstance"][];
+            /** Format: uint64 */
+            armor: number;
+            /** Format: uint64 */
+            block: number;
+            /** @description Only present for the viewer's own character and characters within its reach */
+            contains?: components["schemas"]["Content"][] | null;
+            /** Format: uint */
+            deck_count: number;
             /** Format: uint64 */
             default_movement: number;
-            discard: components["schemas"]["CardInstance"][];
-            effects: components["schemas"]["EffectId"][];
-            hand: components["schemas"]["CardInstance"][];
+            /** @description Only present for the viewer's own character */
+            discard?: components["schemas"]["CardInstance"][] | null;
+            /** Format: uint */
+            discard_count: number;
+            effects: components["schemas"]["EffectInstance"][];
+            /** Format: uint64 */
+            energy: number;
+            /** @description Only present for the viewer's own character */
+            hand?: components["schemas"]["CardInstance"][] | null;
+            /** Format: uint */
+            hand_count: number;
             /** Format: uint */
             hand_size: number;
             health: components["schemas"]["Health"];
             id: components["schemas"]["CharacterId"];
             image: string;
+            /** Format: uint64 */
+            max_energy?: number | null;
             max_health: components["schemas"]["Health"];
             /** Format: uint64 */
             movement: number;
@@ -142,6 +433,8 @@ export interface components {
             race: components["schemas"]["CharacterRace"];
             /** Format: uint64 */
             remaining_actions: number;
+            /** Format: uint64 */
+            shield: number;
         };
         /** Format: uint */
         CharacterId: number;
@@ -152,16 +445,45 @@ export interface components {
         } | {
             Object: components["schemas"]["ObjectInstance"];
         };
+        /** @enum {string} */
+        DamageType: DamageType;
+        /**
+         * @description Protection that absorbs damage before health. Armor reduces the damage of every hit, block
+         *     absorbs damage until the start of the character's next turn and shield until it is used up.
+         * @enum {string}
+         */
+        Defense: Defense;
         Effect: {
             actions: components["schemas"]["CardAction"][];
             description: string;
+            /**
+             * Format: uint64
+             * @description Turns the effect lasts for after it was last applied, if it is not permanent
+             */
+            duration?: number | null;
             id: components["schemas"]["EffectId"];
             image: string;
+            /** Format: uint64 */
+            max_stacks?: number | null;
             name: string;
+            stacking: components["schemas"]["Stacking"];
             triggers: components["schemas"]["Trigger"][];
         };
         /** Format: uint */
         EffectId: number;
+        /** @description An effect on a character. Its actions are scaled by its stacks. */
+        EffectInstance: {
+            effect_id: components["schemas"]["EffectId"];
+            /**
+             * Format: uint64
+             * @description Turns of the affected character left until the effect wears off, if it is not permanent
+             */
+            remaining_turns?: number | null;
+            /** @description The character that last applied the effect, if any */
+            source?: components["schemas"]["CharacterId"] | null;
+            /** Format: uint64 */
+            stacks: number;
+        };
         EndCondition: {
             condition: components["schemas"]["EndConditionCriterion"];
             condition_type: components["schemas"]["EndConditionType"];
@@ -187,6 +509,12 @@ export interface components {
             /** Format: uint */
             width: number;
         };
+        GridLocation: {
+            /** Format: uint */
+            x: number;
+            /** Format: uint */
+            y: number;
+        };
         /** Format: uint64 */
         Health: number;
         Object: {
@@ -203,11 +531,30 @@ export interface components {
         };
         /** Format: uint */
         ObjectInstanceId: number;
+        /** @enum {string} */
+        Pile: Pile;
+        /**
+         * @description What applying an effect does to a character that already has it. Add gives it another stack,
+         *     Refresh only restarts its duration and Ignore leaves it as it is.
+         * @enum {string}
+         */
+        Stacking: Stacking;
         StoryCardEntry: {
             h1: string;
         } | {
             p: string;
         };
+        TakeActionItem: {
+            Card: [
+                number,
+                number
+            ];
+        } | {
+            Object: [
+                number,
+                number
+            ];
+        };
         /** @enum {string} */
         Target: Target;
         Team: {
@@ -238,11 +585,14 @@ export interface components {
     pathItems: never;
 }
 export type BattleState = components['schemas']['"BattleState"'];
+export type Action = components['schemas']['Action'];
 export type Battle = components['schemas']['Battle'];
+export type BattleEvent = components['schemas']['BattleEvent'];
 export type Board = components['schemas']['Board'];
 export type BoardItem = components['schemas']['BoardItem'];
 export type Card = components['schemas']['Card'];
 export type CardAction = components['schemas']['CardAction'];
+export type CardChoice = components['schemas']['CardChoice'];
 export type CardId = components['schemas']['CardId'];
 export type CardInstance = components['schemas']['CardInstance'];
 export type CardInstanceId = components['schemas']['CardInstanceId'];
@@ -252,15 +602,18 @@ export type CharacterId = components['schemas']['CharacterId'];
 export type Content = components['schemas']['Content'];
 export type Effect = components['schemas']['Effect'];
 export type EffectId = components['schemas']['EffectId'];
+export type EffectInstance = components['schemas']['EffectInstance'];
 export type EndCondition = components['schemas']['EndCondition'];
 export type EndConditionCriterion = components['schemas']['EndConditionCriterion'];
 export type Grid = components['schemas']['Grid'];
+export type GridLocation = components['schemas']['GridLocation'];
 export type Health = components['schemas']['Health'];
 export type Object = components['schemas']['Object'];
 export type ObjectId = components['schemas']['ObjectId'];
 export type ObjectInstance = components['schemas']['ObjectInstance'];
 export type ObjectInstanceId = components['schemas']['ObjectInstanceId'];
 export type StoryCardEntry = components['schemas']['StoryCardEntry'];
+export type TakeActionItem = components['schemas']['TakeActionItem'];
 export type Team = components['schemas']['Team'];
 export type TeamId = components['schemas']['TeamId'];
 export type TemplateEntry = components['schemas']['TemplateEntry'];
@@ -271,14 +624,40 @@ export enum BattleTextEntry {
     Attack = "Attack",
     Damage = "Damage"
 }
+export enum CardChoiceKind {
+    Discard = "Discard",
+    Exhaust = "Exhaust",
+    Reorder = "Reorder"
+}
 export enum CharacterRace {
     Human = "Human",
     Machine = "Machine"
 }
+export enum DamageType {
+    Physical = "Physical",
+    Fire = "Fire",
+    Poison = "Poison",
+    Electric = "Electric"
+}
+export enum Defense {
+    Armor = "Armor",
+    Block = "Block",
+    Shield = "Shield"
+}
 export enum EndConditionType {
     win = "win",
     loss = "loss"
 }
+export enum Pile {
+    Hand = "Hand",
+    Deck = "Deck",
+    Discard = "Discard"
+}
+export enum Stacking {
+    Add = "Add",
+    Refresh = "Refresh",
+    Ignore = "Ignore"
+}
 export enum Target {
     Me = "Me",
     Others = "Others",
@@ -286,6 +665,13 @@ export enum Target {
 }
 export enum Trigger {
     Death = "Death",
-    TurnStart = "TurnStart"
+    TurnStart = "TurnStart",
+    TurnEnd = "TurnEnd",
+    RoundStart = "RoundStart",
+    RoundEnd = "RoundEnd",
+    OnDamaged = "OnDamaged",
+    OnHeal = "OnHeal",
+    OnCardPlayed = "OnCardPlayed",
+    OnMove = "OnMove"
 }
 export type operations = Record<string, never>;
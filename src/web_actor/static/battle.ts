@@ -12,6 +12,17 @@ import { Target } from "./battle-schema.generated";
 
 export { Target as ActionTarget };
 
+/** Sent over the `action_rejected` event when the battle refuses an action */
+export interface ActionRejected {
+  message: string;
+}
+
+/** Body of the response when the server refuses a request */
+export interface RequestError {
+  error: unknown;
+  message: string;
+}
+
 export type TypedText = TemplateEntry;
 export type BattleHistoryEntry = TypedText[];
 export type StoryCard = StoryCardEntry[];
@@ -1,8 +1,12 @@
+import { isSpectating, withSeat } from "./utils";
+
 let evtSource: EventSource;
 
 export function init(onConnected?: () => void) {
   console.log("Init sse");
-  evtSource = new EventSource("/sse");
+  evtSource = new EventSource(
+    isSpectating() ? "/spectate" : withSeat("/sse"),
+  );
   evtSource.onopen = onConnected ?? null;
 
   evtSource.onerror = (err) => {
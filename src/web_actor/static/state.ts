@@ -1,21 +1,47 @@
-import type { CardInstance, CharacterId, ObjectInstance } from "./battle";
+import type {
+  CardInstance,
+  CharacterId,
+  ObjectInstance,
+  RequestError,
+} from "./battle";
 
-import type { Coordinate } from "./utils";
+import { type Coordinate, isSpectating, withSeat } from "./utils";
 
 /* eslint camelcase: "off" */
 
-export async function takeAction(card: CardInstance, targetId: CharacterId) {
-  await fetch("/act", {
+let onRequestError: ((message: string) => void) | undefined;
+
+// Called with the message of every request the server refuses
+export function setRequestErrorHandler(handler?: (message: string) => void) {
+  onRequestError = handler;
+}
+
+// Spectators may not act, even though a lone player's seat can be used without a token
+async function post(path: string, body?: object) {
+  if (isSpectating()) {
+    return;
+  }
+  const response = await fetch(withSeat(path), {
     method: "POST",
-    headers: {
-      "Content-Type": "application/json",
-    },
-    body: JSON.stringify({
-      card_id: card.card_id,
-      card_instance_id: card.card_instance_id,
-      target_id: targetId,
+    ...(body && {
+      headers: {
+        "Content-Type": "application/json",
+      },
+      body: JSON.stringify(body),
     }),
   });
+  if (!response.ok) {
+    const { message } = (await response.json()) as RequestError;
+    onRequestError?.(message);
+  }
+}
+
+export async function takeAction(card: CardInstance, targetId: CharacterId) {
+  await post("/act", {
+    card_id: card.card_id,
+    card_instance_id: card.card_instance_id,
+    target_id: targetId,
+  });
 }
 
 export async function takeContent(
@@ -23,34 +49,28 @@ export async function takeContent(
   from: Coordinate,
   item: { card: CardInstance } | { object: ObjectInstance },
 ) {
-  await fetch("/take", {
-    method: "POST",
-    headers: {
-      "Content-Type": "application/json",
-    },
-    body: JSON.stringify({
-      target_id: targetId,
-      from,
-      item,
-    }),
+  await post("/take", {
+    target_id: targetId,
+    from,
+    item,
   });
 }
 
 export async function move(targetId: CharacterId, to: Coordinate) {
-  await fetch("/move", {
-    method: "POST",
-    headers: {
-      "Content-Type": "application/json",
-    },
-    body: JSON.stringify({
-      target_id: targetId,
-      to,
-    }),
+  await post("/move", {
+    target_id: targetId,
+    to,
   });
 }
 
 export async function pass() {
-  await fetch("/pass", {
-    method: "POST",
-  });
+  await post("/pass");
+}
+
+export async function undo() {
+  await post("/undo");
+}
+
+export async function choose(cards: CardInstance[]) {
+  await post("/choose", { cards });
 }
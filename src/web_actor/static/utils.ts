@@ -3,6 +3,7 @@ import type {
   Battle,
   BattleState,
   Card,
+  CardInstance,
   CardAction,
   Character,
   CharacterId,
@@ -54,6 +55,21 @@ export function getCardTarget(card: Card): ActionTarget {
   return defaultTarget;
 }
 
+/** Characters the player may currently play the card on, as reported by the server */
+export function getLegalTargets(
+  battleState: BattleState,
+  cardInstance: CardInstance,
+): CharacterId[] {
+  return battleState.legal_actions.flatMap((action) =>
+    action !== "Pass" &&
+    "Act" in action &&
+    action.Act[0].card_id === cardInstance.card_id &&
+    action.Act[0].card_instance_id === cardInstance.card_instance_id
+      ? [action.Act[1]]
+      : [],
+  );
+}
+
 export function getLivingCharacters(battle: Battle): Character[] {
   return Object.values(battle.characters).filter(
     (character) => character.health > 0,
@@ -77,6 +93,17 @@ export function cssUrl(path: string): string {
   return `url(${path})`;
 }
 
+// Appends the seat token the page was opened with, which tells the server which player is calling.
+export function withSeat(path: string): string {
+  const seat = new URLSearchParams(window.location.search).get("seat");
+  return seat === null ? path : `${path}?seat=${encodeURIComponent(seat)}`;
+}
+
+// Spectators open the page with `?spectate` and only watch the battle.
+export function isSpectating(): boolean {
+  return new URLSearchParams(window.location.search).has("spectate");
+}
+
 // Like `assetPath()` but returns a CSS url() string.
 export function assetUrl(rawAssetPath: string): string {
   return cssUrl(assetPath(rawAssetPath));
@@ -100,14 +127,6 @@ export function isAdjacent(
   );
 }
 
-export function countEntries<T>(entries: readonly T[]): Map<T, number> {
-  const ret = new Map<T, number>();
-  for (const entry of entries) {
-    ret.set(entry, (ret.get(entry) ?? 0) + 1);
-  }
-  return ret;
-}
-
 export function getCharacterCoordinate(
   battle: Battle,
   characterId: CharacterId,
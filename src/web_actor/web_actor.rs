@@ -1,99 +1,294 @@
 use super::{
-    handlers::{ArcEventSender, BattleServerEvent, ServerState},
-    server::Server,
+    battle_view::BattleView,
+    event_hub::EventHub,
+    handlers::{ArcServerState, BattleServerEvent, RequestError, Seat, ServerState},
+    server::{Server, ServerConfig},
+};
+use crate::{
+    Action, ActionLogEntry, ActionRejection, ActionResult, Actor, Battle, BattleEvent,
+    BattleObserver, CardChoice, CardInstance, CharacterId,
 };
-use crate::{ActionResult, Actor, Battle, CharacterId};
 use actix_web_lab::sse;
 use async_trait::async_trait;
 use schemars::JsonSchema;
 use serde::Serialize;
-use std::{path::Path, sync::Arc};
+use std::{
+    path::Path,
+    sync::{
+        self, Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Instant,
+};
 use tokio::sync::{
     Mutex,
-    mpsc::{Receiver, channel, error::SendError},
+    mpsc::{Receiver, channel},
 };
 
 #[derive(Serialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct BattleState<'battle> {
-    battle: &'battle Battle,
+    battle: BattleView<'battle>,
     character_id: CharacterId,
+    /// Everything the character may do right now
+    legal_actions: Vec<Action>,
+    /// Whether the character may take back its last action
+    can_undo: bool,
+    /// Cards the character has to pick from before anything else happens
+    choice: Option<&'battle CardChoice>,
+    /// Milliseconds left before the acting character runs out of time, when turns are limited
+    turn_time_remaining: Option<u64>,
+    /// Set for read-only viewers. Their state follows the acting character but only reveals what
+    /// is public.
+    spectating: bool,
+}
+/// Sent to the client when the battle refuses one of its actions
+#[derive(Serialize)]
+struct ActionRejected<'a> {
+    action: &'a Action,
+    rejection: &'a ActionRejection,
+    message: String,
 }
+
+/// A web server shared by every `WebActor` of a battle, with one seat per player
+pub struct WebServer {
+    server: Server<ServerState>,
+    state: ArcServerState,
+    spectators: EventHub,
+    /// The character whose turn spectators are watching
+    acting: std::sync::Mutex<Option<CharacterId>>,
+}
+
+impl WebServer {
+    pub fn new(
+        config: &ServerConfig,
+        additional_asset_directory: Option<&Path>,
+    ) -> Result<Arc<Self>, std::io::Error> {
+        let spectators = EventHub::default();
+        let state = Arc::new(Mutex::new(ServerState {
+            spectators: spectators.clone(),
+            ..Default::default()
+        }));
+        Ok(Arc::new(Self {
+            server: Server::new(state.clone(), config, additional_asset_directory)?,
+            state,
+            spectators,
+            acting: Default::default(),
+        }))
+    }
+
+    /// Base URL the server can be reached at, including the port it actually bound to
+    pub fn url(&self) -> &str {
+        self.server.url()
+    }
+}
+
+impl WebServer {
+    fn send_spectator_state(&self, battle: &Battle) {
+        let Some(character_id) = *self.acting.lock().unwrap() else {
+            return;
+        };
+        self.spectators.send_retained(event(
+            "battle_state",
+            BattleState {
+                battle: BattleView::new(battle, None),
+                character_id,
+                legal_actions: vec![],
+                can_undo: false,
+                choice: None,
+                turn_time_remaining: turn_time_remaining(battle),
+                spectating: true,
+            },
+        ));
+    }
+}
+
+impl BattleObserver for WebServer {
+    fn on_turn_start(&self, battle: &Battle, character_id: CharacterId) {
+        self.acting.lock().unwrap().replace(character_id);
+        self.send_spectator_state(battle);
+    }
+
+    fn on_action(&self, battle: &Battle, _entry: &ActionLogEntry) {
+        self.send_spectator_state(battle);
+    }
+
+    fn on_round_end(&self, battle: &Battle) {
+        self.send_spectator_state(battle);
+    }
+
+    fn on_event(&self, battle: &Battle, event: &BattleEvent) {
+        if matches!(event, BattleEvent::BattleEnded { .. }) {
+            self.send_spectator_state(battle);
+        }
+    }
+}
+
 pub struct WebActor {
     character_id: CharacterId,
-    #[expect(dead_code)] // Required to stay alive during lifetime of WebActor
-    server: Server<ServerState>,
-    event_tx: ArcEventSender,
+    // Required to stay alive during lifetime of WebActor
+    _server: Arc<WebServer>,
+    events: EventHub,
     action_rx: Arc<Mutex<Receiver<BattleServerEvent>>>,
+    acting: Arc<sync::Mutex<Option<u64>>>,
+    prompts: AtomicU64,
 }
 
 impl WebActor {
-    pub async fn new(
-        character_id: CharacterId,
-        additional_asset_directory: Option<&Path>,
-    ) -> Result<Self, std::io::Error> {
+    /// Takes a seat for the character on the server and prints the URL its player joins from
+    pub async fn new(character_id: CharacterId, server: Arc<WebServer>) -> Self {
         let (action_tx, action_rx) = channel(1);
+        let events = EventHub::default();
+        let acting = Arc::<sync::Mutex<Option<u64>>>::default();
 
-        let event_tx = ArcEventSender::default();
-
-        let server = Server::new(
-            Arc::new(Mutex::new(ServerState {
-                event_tx: event_tx.clone(),
-                action_tx: action_tx.clone(),
-            })),
-            additional_asset_directory,
-        )?;
+        let token = format!("{:016x}", rand::random::<u64>());
+        println!(
+            "Character {character_id} joins at {}/index.html?seat={token}",
+            server.url()
+        );
+        server.state.lock().await.seats.insert(
+            token,
+            Seat {
+                events: events.clone(),
+                action_tx,
+                acting: acting.clone(),
+            },
+        );
 
-        Ok(Self {
+        Self {
             character_id,
-            server,
-            event_tx,
+            _server: server,
+            events,
             action_rx: Arc::new(Mutex::new(action_rx)),
-        })
-    }
-
-    async fn send_battle_state(&self, battle: &Battle) -> Result<(), SendError<sse::Event>> {
-        if let Some(sender) = self.event_tx.lock().await.as_ref() {
-            sender
-                .send(
-                    sse::Data::new_json(BattleState {
-                        battle,
-                        character_id: self.character_id,
-                    })
-                    .unwrap()
-                    .event("battle_state")
-                    .into(),
-                )
-                .await?;
+            acting,
+            prompts: AtomicU64::new(0),
         }
-        Ok(())
     }
-}
 
-#[async_trait]
-impl Actor for WebActor {
-    fn get_character_id(&self) -> &CharacterId {
-        &self.character_id
+    /// Sends the battle state to every connection of the seat, including those opened later
+    fn send_battle_state(&self, battle: &Battle) {
+        self.events.send_retained(event(
+            "battle_state",
+            BattleState {
+                battle: BattleView::new(battle, Some(self.character_id)),
+                character_id: self.character_id,
+                legal_actions: battle.legal_actions(self.character_id),
+                can_undo: battle.undo_history.check().is_ok(),
+                choice: battle
+                    .pending_choice
+                    .as_ref()
+                    .filter(|choice| choice.character_id == self.character_id),
+                turn_time_remaining: turn_time_remaining(battle),
+                spectating: false,
+            },
+        ));
     }
 
-    async fn act(&self, battle: &Battle) -> ActionResult {
-        self.send_battle_state(battle).await?;
+    async fn wait_for_action(&self, battle: &Battle, prompt: u64) -> ActionResult {
+        self.send_battle_state(battle);
         loop {
             match self.action_rx.lock().await.recv().await {
                 Some(BattleServerEvent::BattleRequest) => {
-                    self.send_battle_state(battle).await?;
+                    self.send_battle_state(battle);
+                }
+                Some(BattleServerEvent::Action(sent_for, _, reply)) if sent_for != prompt => {
+                    let _ = reply.send(Err(RequestError::NotYourTurn));
                 }
-                Some(BattleServerEvent::Action(action)) => {
-                    return action;
+                Some(BattleServerEvent::Action(_, action, reply)) => {
+                    // Checked here, against the battle the action is for, so the client learns
+                    // from the response to its request whether the action was applied
+                    match battle.validate_action(&self.character_id, &action) {
+                        Ok(()) => {
+                            let _ = reply.send(Ok(()));
+                            return Ok(action);
+                        }
+                        Err(rejection) => {
+                            let _ = reply.send(Err(RequestError::Rejected(rejection)));
+                        }
+                    }
                 }
                 None => {}
             }
         }
     }
+}
+
+fn turn_time_remaining(battle: &Battle) -> Option<u64> {
+    battle.turn_deadline.map(|deadline| {
+        deadline
+            .saturating_duration_since(Instant::now())
+            .as_millis() as u64
+    })
+}
+
+fn event(name: &str, data: impl Serialize) -> sse::Event {
+    sse::Data::new_json(data)
+        .expect("Event data should serialize")
+        .event(name)
+        .into()
+}
+
+/// Marks the actor as acting on a new prompt until dropped
+struct ActingGuard<'actor>(&'actor WebActor);
+
+impl<'actor> ActingGuard<'actor> {
+    fn new(actor: &'actor WebActor) -> (Self, u64) {
+        let prompt = actor.prompts.fetch_add(1, Ordering::Relaxed);
+        *actor.acting.lock().unwrap() = Some(prompt);
+        (Self(actor), prompt)
+    }
+}
+
+impl Drop for ActingGuard<'_> {
+    fn drop(&mut self) {
+        *self.0.acting.lock().unwrap() = None;
+
+        // Actions already sent for the prompt are refused now rather than once the next one comes
+        if let Ok(mut action_rx) = self.0.action_rx.try_lock() {
+            while let Ok(event) = action_rx.try_recv() {
+                if let BattleServerEvent::Action(_, _, reply) = event {
+                    let _ = reply.send(Err(RequestError::NotYourTurn));
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Actor for WebActor {
+    fn get_character_id(&self) -> &CharacterId {
+        &self.character_id
+    }
+
+    async fn act(&self, battle: &Battle) -> ActionResult {
+        // Also ends acting when the battle gives up waiting because the turn ran out of time
+        let (_acting, prompt) = ActingGuard::new(self);
+        self.wait_for_action(battle, prompt).await
+    }
+
+    async fn choose_cards(&self, battle: &Battle, choice: &CardChoice) -> Vec<CardInstance> {
+        let (_acting, prompt) = ActingGuard::new(self);
+        match self.wait_for_action(battle, prompt).await {
+            Ok(Action::Choose(picked)) => picked,
+            _ => choice.default_pick(),
+        }
+    }
+
+    async fn on_action_rejected(
+        &self,
+        _battle: &Battle,
+        action: &Action,
+        rejection: &ActionRejection,
+    ) {
+        let rejected = ActionRejected {
+            action,
+            rejection,
+            message: rejection.to_string(),
+        };
+        self.events.send(event("action_rejected", rejected));
+    }
 
     async fn on_game_over(&self, battle: &Battle) {
-        self.send_battle_state(battle)
-            .await
-            .expect("Failed to send game over state");
+        self.send_battle_state(battle);
     }
 }
@@ -27,6 +27,15 @@ macro_rules! DeclareWrappedType {
             }
         }
 
+        impl<'de> serde::Deserialize<'de> for $struct_name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                <$field_type as serde::Deserialize>::deserialize(deserializer).map(Self::new)
+            }
+        }
+
         impl JsonSchema for $struct_name {
             fn schema_name() -> std::borrow::Cow<'static, str> {
                 stringify!($struct_name).into()